@@ -0,0 +1,73 @@
+//! Throughput of concurrent tool calls over the SSE transport.
+//!
+//! Each call sleeps for a fixed time, so the elapsed time per batch shows how many calls
+//! the server runs in parallel: a batch of N fully parallel calls takes about as long as
+//! one call, while serialized calls take N times as long.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::future::join_all;
+use offeryn_core::client::SseClientTransport;
+use offeryn_core::transport::SseServerTransport;
+use offeryn_core::{McpClient, McpServer};
+use offeryn_derive::tool;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+const CALL_DURATION: Duration = Duration::from_millis(5);
+
+#[derive(Default)]
+struct Worker {}
+
+#[tool]
+impl Worker {
+    /// Simulates a tool waiting on I/O
+    async fn work(&self) -> String {
+        tokio::time::sleep(CALL_DURATION).await;
+        "done".to_string()
+    }
+}
+
+async fn connect() -> McpClient {
+    let server = Arc::new(McpServer::new("bench-server", "1.0.0"));
+    server.register_tools(Worker::default()).await;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = SseServerTransport::create_router(server);
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    McpClient::connect(
+        SseClientTransport::new(&format!("http://{}/sse", addr)),
+        "bench-client",
+        "1.0.0",
+    )
+    .await
+    .unwrap()
+}
+
+fn parallel_tool_calls(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let client = runtime.block_on(connect());
+
+    let mut group = c.benchmark_group("sse_parallel_tool_calls");
+    for concurrency in [1, 8, 32] {
+        group.throughput(Throughput::Elements(concurrency));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| {
+                    join_all((0..concurrency).map(|_| client.call_tool("worker_work", json!({}))))
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parallel_tool_calls);
+criterion_main!(benches);
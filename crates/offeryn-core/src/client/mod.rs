@@ -0,0 +1,338 @@
+pub mod transport;
+
+pub use transport::{
+    ChildProcessTransport, ClientTransport, SseClientTransport, StdioClientTransport,
+};
+
+use crate::ClientError;
+use jsonrpc_core::{
+    Call, Failure, Id, MethodCall, Notification, Output, Params, Response as JsonRpcResponse,
+    Success, Version,
+};
+use offeryn_types::*;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Output>>>>;
+
+/// A Model Context Protocol client.
+///
+/// `McpClient::connect` starts a `ClientTransport`, performs the `initialize` handshake and
+/// sends `notifications/initialized`; the returned client is ready for use. Responses are
+/// matched to requests by JSON-RPC id, so requests may be issued concurrently.
+pub struct McpClient {
+    rpc: RpcChannel,
+    notifications: broadcast::Sender<Notification>,
+    server: InitializeResult,
+    reader: JoinHandle<()>,
+}
+
+/// The outgoing half of a connection: issues requests and notifications and tracks the
+/// requests still awaiting a response.
+struct RpcChannel {
+    outgoing: mpsc::Sender<Value>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    pub async fn connect<T: ClientTransport>(
+        transport: T,
+        name: &str,
+        version: &str,
+    ) -> Result<Self, ClientError> {
+        let (outgoing, incoming) = transport.start().await?;
+        let pending = PendingRequests::default();
+        let (notifications, _) = broadcast::channel(100);
+
+        let reader = tokio::spawn(Self::read_loop(
+            incoming,
+            outgoing.clone(),
+            pending.clone(),
+            notifications.clone(),
+        ));
+
+        let rpc = RpcChannel {
+            outgoing,
+            pending,
+            next_id: AtomicU64::new(1),
+        };
+
+        let server = match Self::initialize(&rpc, name, version).await {
+            Ok(server) => server,
+            Err(e) => {
+                // The reader holds a sender, so it must go for the transport to shut down
+                reader.abort();
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            rpc,
+            notifications,
+            server,
+            reader,
+        })
+    }
+
+    /// Performs the `initialize` handshake and sends `notifications/initialized`.
+    async fn initialize(
+        rpc: &RpcChannel,
+        name: &str,
+        version: &str,
+    ) -> Result<InitializeResult, ClientError> {
+        let server: InitializeResult = rpc
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": LATEST_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": name, "version": version },
+                }),
+            )
+            .await?;
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&server.protocol_version.as_str()) {
+            warn!(
+                protocol_version = %server.protocol_version,
+                "Server negotiated an unsupported protocol version"
+            );
+            return Err(ClientError::UnsupportedProtocolVersion(
+                server.protocol_version,
+            ));
+        }
+        info!(
+            server_name = %server.server_info.name,
+            server_version = %server.server_info.version,
+            protocol_version = %server.protocol_version,
+            "Connected to MCP server"
+        );
+        rpc.notify("notifications/initialized", Value::Null).await?;
+        Ok(server)
+    }
+
+    /// The server's response to the `initialize` handshake.
+    pub fn server(&self) -> &InitializeResult {
+        &self.server
+    }
+
+    /// Subscribes to notifications sent by the server, such as
+    /// `notifications/resources/updated`.
+    pub fn notifications(&self) -> broadcast::Receiver<Notification> {
+        self.notifications.subscribe()
+    }
+
+    pub async fn list_tools(&self) -> Result<ListToolsResult, ClientError> {
+        self.request("tools/list", Value::Null).await
+    }
+
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, ClientError> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    pub async fn list_resources(&self) -> Result<ListResourcesResult, ClientError> {
+        self.request("resources/list", Value::Null).await
+    }
+
+    pub async fn list_resource_templates(
+        &self,
+    ) -> Result<ListResourceTemplatesResult, ClientError> {
+        self.request("resources/templates/list", Value::Null).await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, ClientError> {
+        self.request("resources/read", json!({ "uri": uri })).await
+    }
+
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<(), ClientError> {
+        self.request::<Value>("resources/subscribe", json!({ "uri": uri }))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<(), ClientError> {
+        self.request::<Value>("resources/unsubscribe", json!({ "uri": uri }))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn list_prompts(&self) -> Result<ListPromptsResult, ClientError> {
+        self.request("prompts/list", Value::Null).await
+    }
+
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<GetPromptResult, ClientError> {
+        self.request(
+            "prompts/get",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    /// Sends an arbitrary request and waits for the matching response.
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, ClientError> {
+        self.rpc.request(method, params).await
+    }
+
+    /// Sends a notification to the server.
+    pub async fn notify(&self, method: &str, params: Value) -> Result<(), ClientError> {
+        self.rpc.notify(method, params).await
+    }
+
+    async fn read_loop(
+        mut incoming: mpsc::Receiver<Value>,
+        outgoing: mpsc::Sender<Value>,
+        pending: PendingRequests,
+        notifications: broadcast::Sender<Notification>,
+    ) {
+        while let Some(message) = incoming.recv().await {
+            if message.get("method").is_some() {
+                match serde_json::from_value::<Call>(message) {
+                    Ok(Call::Notification(notification)) => {
+                        debug!(method = %notification.method, "Received notification");
+                        let _ = notifications.send(notification);
+                    }
+                    Ok(Call::MethodCall(call)) if call.method == "ping" => {
+                        debug!("Answering ping");
+                        let response = JsonRpcResponse::Single(Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result: json!({}),
+                            id: call.id,
+                        }));
+                        if let Ok(response) = serde_json::to_value(response) {
+                            let _ = outgoing.send(response).await;
+                        }
+                    }
+                    Ok(Call::MethodCall(call)) => {
+                        // Other server-to-client requests are not supported yet; answer rather
+                        // than leave the server waiting.
+                        warn!(method = %call.method, "Rejecting server-initiated request");
+                        let response = JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: jsonrpc_core::Error::method_not_found(),
+                            id: call.id,
+                        }));
+                        if let Ok(response) = serde_json::to_value(response) {
+                            let _ = outgoing.send(response).await;
+                        }
+                    }
+                    _ => warn!("Discarding invalid message from server"),
+                }
+                continue;
+            }
+
+            match serde_json::from_value::<Output>(message) {
+                Ok(output) => match output.id() {
+                    Id::Num(id) => match pending.lock().unwrap().remove(id) {
+                        Some(tx) => {
+                            let _ = tx.send(output);
+                        }
+                        None => warn!(id = %id, "Received response for unknown request"),
+                    },
+                    id => warn!(id = ?id, "Received response with unexpected id"),
+                },
+                Err(e) => warn!(error = %e, "Discarding invalid message from server"),
+            }
+        }
+        debug!("Server connection closed");
+        // Dropping the senders fails every request still waiting for a response
+        pending.lock().unwrap().clear();
+    }
+}
+
+impl RpcChannel {
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, ClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        // Forgets the request if this future is dropped before the response arrives
+        let _pending = PendingGuard {
+            pending: &self.pending,
+            id,
+        };
+
+        let call = Call::MethodCall(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params: to_params(params),
+            id: Id::Num(id),
+        });
+        debug!(method = %method, id = %id, "Sending request");
+        if self
+            .outgoing
+            .send(serde_json::to_value(call)?)
+            .await
+            .is_err()
+        {
+            return Err(ClientError::ConnectionClosed);
+        }
+
+        match rx.await.map_err(|_| ClientError::ConnectionClosed)? {
+            Output::Success(success) => Ok(serde_json::from_value(success.result)?),
+            Output::Failure(failure) => Err(ClientError::Rpc(failure.error)),
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), ClientError> {
+        let call = Call::Notification(Notification {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params: to_params(params),
+        });
+        self.outgoing
+            .send(serde_json::to_value(call)?)
+            .await
+            .map_err(|_| ClientError::ConnectionClosed)
+    }
+}
+
+/// Removes a request from the pending map when dropped. Once the response has been
+/// delivered the entry is already gone and this does nothing.
+struct PendingGuard<'a> {
+    pending: &'a PendingRequests,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+fn to_params(params: Value) -> Params {
+    match params {
+        Value::Object(map) => Params::Map(map),
+        Value::Array(values) => Params::Array(values),
+        _ => Params::None,
+    }
+}
@@ -0,0 +1,102 @@
+use super::{ClientTransport, StdioClientTransport};
+use crate::ClientError;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Spawns an MCP server binary and talks to it over its stdin/stdout.
+///
+/// The child's stderr is inherited so server logs stay visible. Once the client side closes,
+/// the child's stdin is closed and it is given a moment to exit before being killed.
+pub struct ChildProcessTransport {
+    command: Command,
+}
+
+impl ChildProcessTransport {
+    pub fn new(command: Command) -> Self {
+        Self { command }
+    }
+}
+
+#[async_trait]
+impl ClientTransport for ChildProcessTransport {
+    async fn start(mut self) -> Result<(mpsc::Sender<Value>, mpsc::Receiver<Value>), ClientError> {
+        let mut child = self
+            .command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+        info!(pid = ?child.id(), "Spawned MCP server process");
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| ClientError::Transport("Child process has no stdin".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| ClientError::Transport("Child process has no stdout".to_string()))?;
+
+        let (stdin_tx, incoming) = StdioClientTransport::with_streams(stdout, stdin)
+            .start()
+            .await?;
+
+        // The child is owned by the task forwarding outgoing messages, so it lives exactly
+        // as long as the client side of the connection
+        let (outgoing, outgoing_rx) = mpsc::channel(100);
+        tokio::spawn(supervise(child, outgoing_rx, stdin_tx));
+
+        Ok((outgoing, incoming))
+    }
+}
+
+/// How long a child gets to exit on its own after its stdin is closed.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Forwards messages to the child's stdin until the client side closes or the child exits,
+/// then makes sure the child is gone.
+async fn supervise(
+    mut child: Child,
+    mut outgoing: mpsc::Receiver<Value>,
+    stdin: mpsc::Sender<Value>,
+) {
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => match message {
+                Some(message) => {
+                    if stdin.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            status = child.wait() => {
+                match status {
+                    Ok(status) => debug!(status = %status, "MCP server process exited"),
+                    Err(e) => warn!(error = %e, "Failed to wait for MCP server process"),
+                }
+                return;
+            }
+        }
+    }
+
+    // Closing stdin asks a well-behaved server to shut down
+    drop(stdin);
+    match tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, child.wait()).await {
+        Ok(Ok(status)) => debug!(status = %status, "MCP server process exited"),
+        Ok(Err(e)) => warn!(error = %e, "Failed to wait for MCP server process"),
+        Err(_) => {
+            info!(pid = ?child.id(), "Killing MCP server process");
+            if let Err(e) = child.start_kill() {
+                warn!(error = %e, "Failed to kill MCP server process");
+            }
+            let _ = child.wait().await;
+        }
+    }
+}
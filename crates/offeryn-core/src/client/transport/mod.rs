@@ -0,0 +1,22 @@
+mod child_process;
+mod sse;
+mod stdio;
+
+pub use child_process::ChildProcessTransport;
+pub use sse::SseClientTransport;
+pub use stdio::StdioClientTransport;
+
+use crate::ClientError;
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// A connection from an `McpClient` to a server.
+///
+/// Starting a transport hands back a pair of channels: JSON-RPC messages sent on the
+/// returned sender are delivered to the server, and every message the server emits arrives
+/// on the returned receiver. The receiver closing signals the connection has gone away.
+#[async_trait]
+pub trait ClientTransport: Send + Sized + 'static {
+    async fn start(self) -> Result<(mpsc::Sender<Value>, mpsc::Receiver<Value>), ClientError>;
+}
@@ -0,0 +1,251 @@
+use super::ClientTransport;
+use crate::ClientError;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Url;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+/// Connects to a server exposed through `SseServerTransport`.
+///
+/// The transport opens the event stream at the given URL, waits for the `endpoint` event
+/// announcing where to POST messages, and from then on delivers every `message` event as
+/// an incoming JSON-RPC message.
+pub struct SseClientTransport {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl SseClientTransport {
+    /// `url` is the full URL of the event stream, e.g. `http://localhost:3000/sse`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_client(url: &str, http: reqwest::Client) -> Self {
+        Self {
+            url: url.to_string(),
+            http,
+        }
+    }
+}
+
+#[async_trait]
+impl ClientTransport for SseClientTransport {
+    async fn start(self) -> Result<(mpsc::Sender<Value>, mpsc::Receiver<Value>), ClientError> {
+        let base = Url::parse(&self.url).map_err(|e| ClientError::Transport(e.to_string()))?;
+        let response = self
+            .http
+            .get(base.clone())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+        info!(url = %base, "Opened SSE stream");
+
+        let mut events = EventStream::new(response.bytes_stream());
+        let endpoint = loop {
+            match events.next().await? {
+                Some(event) if event.event == "endpoint" => {
+                    break base
+                        .join(event.data.trim())
+                        .map_err(|e| ClientError::Transport(e.to_string()))?;
+                }
+                Some(event) => debug!(event = %event.event, "Ignoring event before endpoint"),
+                None => return Err(ClientError::ConnectionClosed),
+            }
+        };
+        info!(endpoint = %endpoint, "Received message endpoint");
+
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<Value>(100);
+        let (incoming_tx, incoming_rx) = mpsc::channel(100);
+
+        tokio::spawn({
+            let http = self.http.clone();
+            async move {
+                // Responses are delivered over the event stream; the POST only acknowledges
+                while let Some(message) = outgoing_rx.recv().await {
+                    let result = http
+                        .post(endpoint.clone())
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(message.to_string())
+                        .send()
+                        .await;
+                    match result {
+                        Ok(response) if !response.status().is_success() => {
+                            warn!(status = %response.status(), "Server rejected message");
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            error!(error = %e, "Failed to post message");
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match events.next().await {
+                    Ok(Some(event)) if event.event == "message" => {
+                        match serde_json::from_str::<Value>(&event.data) {
+                            Ok(message) => {
+                                if incoming_tx.send(message).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(e) => warn!(error = %e, "Discarding malformed message"),
+                        }
+                    }
+                    Ok(Some(event)) => debug!(event = %event.event, "Ignoring SSE event"),
+                    Ok(None) => {
+                        debug!("SSE stream closed");
+                        break;
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "SSE stream failed");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok((outgoing_tx, incoming_rx))
+    }
+}
+
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+/// Incremental parser for a `text/event-stream` response body.
+///
+/// Chunks are buffered as raw bytes and only whole events are decoded, since a chunk may
+/// end in the middle of a UTF-8 character or a `\r\n`.
+struct EventStream {
+    body: futures::stream::BoxStream<'static, reqwest::Result<axum::body::Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl EventStream {
+    fn new(
+        body: impl futures::Stream<Item = reqwest::Result<axum::body::Bytes>> + Send + 'static,
+    ) -> Self {
+        Self {
+            body: body.boxed(),
+            buffer: Vec::new(),
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<SseEvent>, ClientError> {
+        loop {
+            if let Some(event) = self.take_event() {
+                return Ok(Some(event));
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => return Err(ClientError::Transport(e.to_string())),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Finds the blank line ending the first buffered event. Returns where the event's
+    /// lines end and where the next event starts.
+    fn event_end(&self) -> Option<(usize, usize)> {
+        let mut line_start = 0;
+        for (i, byte) in self.buffer.iter().enumerate() {
+            if *byte != b'\n' {
+                continue;
+            }
+            let line = &self.buffer[line_start..i];
+            if line.is_empty() || line == b"\r" {
+                return Some((line_start, i + 1));
+            }
+            line_start = i + 1;
+        }
+        None
+    }
+
+    fn take_event(&mut self) -> Option<SseEvent> {
+        loop {
+            let (end, next) = self.event_end()?;
+            let block: Vec<u8> = self.buffer.drain(..next).collect();
+            let block = String::from_utf8_lossy(&block[..end]);
+
+            let mut event = SseEvent {
+                event: "message".to_string(),
+                data: String::new(),
+            };
+            let mut has_data = false;
+            for line in block.lines() {
+                let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match field {
+                    "event" => event.event = value.to_string(),
+                    "data" => {
+                        if has_data {
+                            event.data.push('\n');
+                        }
+                        event.data.push_str(value);
+                        has_data = true;
+                    }
+                    // Comments (keep-alives) and unknown fields
+                    _ => {}
+                }
+            }
+
+            if has_data {
+                return Some(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `body` delivered one byte per chunk.
+    async fn events(body: &str) -> Vec<(String, String)> {
+        let chunks: Vec<reqwest::Result<axum::body::Bytes>> = body
+            .as_bytes()
+            .iter()
+            .map(|byte| Ok(axum::body::Bytes::copy_from_slice(&[*byte])))
+            .collect();
+        let mut stream = EventStream::new(futures::stream::iter(chunks));
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await.unwrap() {
+            events.push((event.event, event.data));
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_events_split_across_chunks() {
+        let body = concat!(
+            "event: endpoint\r\ndata: /message?sessionId=1\r\n\r\n",
+            ": keep-alive\n\n",
+            "data: {\"text\":\"Grüße, 世界 🦀\"}\n\n",
+            "event: message\r\ndata: first\r\ndata: second\r\n\r\n",
+        );
+        assert_eq!(
+            events(body).await,
+            vec![
+                ("endpoint".to_string(), "/message?sessionId=1".to_string()),
+                (
+                    "message".to_string(),
+                    "{\"text\":\"Grüße, 世界 🦀\"}".to_string()
+                ),
+                ("message".to_string(), "first\nsecond".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use super::ClientTransport;
+use crate::ClientError;
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Speaks newline-delimited JSON-RPC over an arbitrary reader/writer pair, the client-side
+/// counterpart of `StdioServerTransport`.
+pub struct StdioClientTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    reader: R,
+    writer: W,
+}
+
+impl<R, W> StdioClientTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    pub fn with_streams(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+}
+
+#[async_trait]
+impl<R, W> ClientTransport for StdioClientTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    async fn start(self) -> Result<(mpsc::Sender<Value>, mpsc::Receiver<Value>), ClientError> {
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<Value>(100);
+        let (incoming_tx, incoming_rx) = mpsc::channel(100);
+
+        tokio::spawn({
+            let mut writer = BufWriter::new(self.writer);
+            async move {
+                while let Some(message) = outgoing_rx.recv().await {
+                    let mut bytes = serde_json::to_vec(&message)?;
+                    bytes.push(b'\n');
+                    writer.write_all(&bytes).await?;
+                    writer.flush().await?;
+                }
+                Ok::<_, std::io::Error>(())
+            }
+        });
+
+        tokio::spawn({
+            let mut reader = BufReader::new(self.reader);
+            async move {
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    match reader.read_line(&mut line).await {
+                        Ok(0) => {
+                            debug!("Server closed the stream");
+                            break;
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!(error = %e, "Failed to read from server");
+                            break;
+                        }
+                    }
+                    match serde_json::from_str::<Value>(&line) {
+                        Ok(message) => {
+                            if incoming_tx.send(message).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => warn!(error = %e, "Discarding malformed message from server"),
+                    }
+                }
+            }
+        });
+
+        Ok((outgoing_tx, incoming_rx))
+    }
+}
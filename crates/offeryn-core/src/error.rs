@@ -1,38 +1,160 @@
 use jsonrpc_core::{Error as JsonRpcError, ErrorCode};
-use std::fmt;
+use serde_json::{json, Value};
 
-#[derive(Debug)]
+/// Why the server could not answer a request. Converted into the JSON-RPC error object sent
+/// to the client, including `message` and, when present, `data`.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum McpError {
-    InvalidRequest,
-    InvalidParams,
-    MethodNotFound,
-    InternalError,
+    /// The message is not a valid request, or not allowed in the session's current state
+    #[error("{message}")]
+    InvalidRequest {
+        message: String,
+        data: Option<Value>,
+    },
+    /// The params are malformed or name something that doesn't exist, such as an unknown tool
+    #[error("{message}")]
+    InvalidParams {
+        message: String,
+        data: Option<Value>,
+    },
+    #[error("{message}")]
+    MethodNotFound {
+        message: String,
+        data: Option<Value>,
+    },
+    #[error("{message}")]
+    ResourceNotFound {
+        message: String,
+        data: Option<Value>,
+    },
+    /// A tool, prompt or resource handler failed
+    #[error("{message}")]
+    ExecutionFailed {
+        message: String,
+        data: Option<Value>,
+    },
+    #[error("{message}")]
+    InternalError {
+        message: String,
+        data: Option<Value>,
+    },
+    /// The client cancelled the request; no response must be sent
+    #[error("Request cancelled")]
+    RequestCancelled,
 }
 
-impl fmt::Display for McpError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl McpError {
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        McpError::InvalidRequest {
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        McpError::InvalidParams {
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        McpError::MethodNotFound {
+            message: format!("Method not found: {}", method),
+            data: Some(json!({ "method": method })),
+        }
+    }
+
+    pub fn resource_not_found(uri: &str) -> Self {
+        McpError::ResourceNotFound {
+            message: "Resource not found".to_string(),
+            data: Some(json!({ "uri": uri })),
+        }
+    }
+
+    pub fn execution_failed(message: impl Into<String>) -> Self {
+        McpError::ExecutionFailed {
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        McpError::InternalError {
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attaches a `data` payload, replacing any already present.
+    pub fn with_data(mut self, value: Value) -> Self {
+        match &mut self {
+            McpError::InvalidRequest { data, .. }
+            | McpError::InvalidParams { data, .. }
+            | McpError::MethodNotFound { data, .. }
+            | McpError::ResourceNotFound { data, .. }
+            | McpError::ExecutionFailed { data, .. }
+            | McpError::InternalError { data, .. } => *data = Some(value),
+            McpError::RequestCancelled => {}
+        }
+        self
+    }
+
+    pub fn data(&self) -> Option<&Value> {
         match self {
-            McpError::InvalidRequest => write!(f, "Invalid request"),
-            McpError::InvalidParams => write!(f, "Invalid parameters"),
-            McpError::MethodNotFound => write!(f, "Method not found"),
-            McpError::InternalError => write!(f, "Internal error"),
+            McpError::InvalidRequest { data, .. }
+            | McpError::InvalidParams { data, .. }
+            | McpError::MethodNotFound { data, .. }
+            | McpError::ResourceNotFound { data, .. }
+            | McpError::ExecutionFailed { data, .. }
+            | McpError::InternalError { data, .. } => data.as_ref(),
+            McpError::RequestCancelled => None,
+        }
+    }
+
+    /// The JSON-RPC error code sent to the client.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            McpError::InvalidRequest { .. } => ErrorCode::InvalidRequest,
+            McpError::InvalidParams { .. } => ErrorCode::InvalidParams,
+            McpError::MethodNotFound { .. } => ErrorCode::MethodNotFound,
+            McpError::ResourceNotFound { .. } => ErrorCode::ServerError(-32002),
+            McpError::ExecutionFailed { .. } => ErrorCode::ServerError(-32000),
+            McpError::InternalError { .. } => ErrorCode::InternalError,
+            McpError::RequestCancelled => ErrorCode::ServerError(-32800),
         }
     }
 }
 
 impl From<serde_json::Error> for McpError {
-    fn from(_: serde_json::Error) -> Self {
-        McpError::InternalError
+    fn from(error: serde_json::Error) -> Self {
+        McpError::internal(format!("Serialization failed: {}", error))
     }
 }
 
 impl From<McpError> for JsonRpcError {
     fn from(error: McpError) -> Self {
-        match error {
-            McpError::InvalidRequest => JsonRpcError::invalid_request(),
-            McpError::InvalidParams => JsonRpcError::invalid_params("Invalid parameters"),
-            McpError::MethodNotFound => JsonRpcError::method_not_found(),
-            McpError::InternalError => JsonRpcError::new(ErrorCode::ServerError(-32000)),
+        JsonRpcError {
+            code: error.code(),
+            message: error.to_string(),
+            data: error.data().cloned(),
         }
     }
 }
+
+/// Errors surfaced by `McpClient`.
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Transport error: {0}")]
+    Transport(String),
+    #[error("Server returned error {}: {}", .0.code.code(), .0.message)]
+    Rpc(JsonRpcError),
+    #[error("Connection closed")]
+    ConnectionClosed,
+    #[error("Server negotiated unsupported protocol version {0}")]
+    UnsupportedProtocolVersion(String),
+}
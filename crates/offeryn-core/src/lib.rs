@@ -1,10 +1,16 @@
+pub mod client;
 pub mod error;
 pub mod server;
 pub mod transport;
 
-pub use error::McpError;
+pub use client::McpClient;
+pub use error::{ClientError, McpError};
 pub use offeryn_types::{
-    CallToolRequest, CallToolResult, Content, InitializeResult, ListToolsResult,
-    ServerCapabilities, ServerInfo, Tool, LATEST_PROTOCOL_VERSION,
+    CallToolRequest, CallToolResult, Content, CreateMessageRequest, CreateMessageResult,
+    Elicitation, InitializeResult, ListResourcesResult, ListToolsResult, ReadResourceResult,
+    Resource, ResourceContents, ResourceTemplate, SamplingMessage, ServerCapabilities, ServerInfo,
+    Tool, LATEST_PROTOCOL_VERSION,
+};
+pub use server::{
+    LoggingLayer, McpServer, NotificationHandler, ResourceNotifier, Session, SessionState,
 };
-pub use server::McpServer;
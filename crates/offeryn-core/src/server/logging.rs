@@ -0,0 +1,183 @@
+use super::session::{Session, SessionMap};
+use offeryn_types::LoggingLevel;
+use serde_json::{Map, Value};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+thread_local! {
+    /// Set while an event is being forwarded, so anything logged along the way is not
+    /// forwarded in turn
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A `tracing_subscriber` layer that sends tracing events to MCP clients as
+/// `notifications/message`.
+///
+/// An event goes to a session if it is emitted while one of the session's requests is
+/// being handled, such as inside a tool call, or inside a span with a `session_id` field
+/// naming the session. Events less severe than the level the client chose with
+/// `logging/setLevel` are dropped, as are the server's own events. The event's target
+/// becomes the logger name.
+///
+/// Obtained from `McpServer::logging_layer`. Like any layer it can be combined with a
+/// filter to limit what is forwarded.
+#[derive(Clone)]
+pub struct LoggingLayer {
+    sessions: SessionMap,
+}
+
+/// The session a span names in its `session_id` field.
+struct SessionTag(String);
+
+impl LoggingLayer {
+    pub(crate) fn new(sessions: SessionMap) -> Self {
+        Self { sessions }
+    }
+
+    fn session_for<S>(&self, event: &Event<'_>, ctx: &Context<'_, S>) -> Option<Arc<Session>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if let Some(session) = Session::current() {
+            return Some(session);
+        }
+        let session_id = ctx.event_scope(event)?.find_map(|span| {
+            span.extensions()
+                .get::<SessionTag>()
+                .map(|tag| tag.0.clone())
+        })?;
+        self.sessions.read().unwrap().get(&session_id).cloned()
+    }
+}
+
+impl<S> Layer<S> for LoggingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = SessionIdVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(session_id), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(SessionTag(session_id));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = SessionIdVisitor::default();
+        values.record(&mut visitor);
+        if let (Some(session_id), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().replace(SessionTag(session_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // The server logs every message it sends, so its own events would echo forever
+        if metadata.target().starts_with("offeryn_core") || FORWARDING.get() {
+            return;
+        }
+        let Some(session) = self.session_for(event, &ctx) else {
+            return;
+        };
+        let level = logging_level(metadata.level());
+        if level < session.log_level() {
+            return;
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        FORWARDING.set(true);
+        session.log(level, Some(metadata.target()), visitor.into_data());
+        FORWARDING.set(false);
+    }
+}
+
+fn logging_level(level: &Level) -> LoggingLevel {
+    match *level {
+        Level::ERROR => LoggingLevel::Error,
+        Level::WARN => LoggingLevel::Warning,
+        Level::INFO => LoggingLevel::Info,
+        Level::DEBUG | Level::TRACE => LoggingLevel::Debug,
+    }
+}
+
+/// Picks the `session_id` field out of a span's fields.
+#[derive(Default)]
+struct SessionIdVisitor(Option<String>);
+
+impl Visit for SessionIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "session_id" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "session_id" {
+            // `%session_id` records through Display, which Debug-formats without quotes
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Collects an event's fields into the `data` of a log message.
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: Value) {
+        match (field.name(), value) {
+            ("message", Value::String(message)) => self.message = Some(message),
+            (name, value) => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// A plain string for events with only a message, an object of all fields otherwise.
+    fn into_data(self) -> Value {
+        if self.fields.is_empty() {
+            return self.message.map(Value::String).unwrap_or_default();
+        }
+        let mut fields = self.fields;
+        if let Some(message) = self.message {
+            fields.insert("message".to_string(), Value::String(message));
+        }
+        Value::Object(fields)
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, Value::String(format!("{:?}", value)));
+    }
+}
@@ -1,17 +1,61 @@
+mod logging;
+mod notifications;
+mod session;
+mod uri_template;
+
+pub use logging::LoggingLayer;
+pub use notifications::NotificationHandler;
+pub(crate) use session::InFlightRequest;
+pub use session::{ResourceNotifier, Session, SessionState};
+
 use crate::McpError;
+use futures::future::join_all;
 use jsonrpc_core::{
-    Call, ErrorCode, Failure, Output, Params, Request as JsonRpcRequest,
-    Response as JsonRpcResponse, Success, Version,
+    Call, Failure, Output, Params, Request as JsonRpcRequest, Response as JsonRpcResponse, Success,
+    Version,
 };
+use notifications::NotificationDispatcher;
 use offeryn_types::*;
+use serde::de::DeserializeOwned;
+use session::SessionMap;
 use std::collections::HashMap;
-use tokio::sync::Mutex;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
 use tracing::{debug, info, warn};
+use uri_template::UriTemplate;
 
 pub struct McpServer {
     name: String,
     version: String,
-    tools: Mutex<HashMap<String, Box<dyn McpTool>>>,
+    /// Tools are cloned out of the registry before they run, so the lock is only ever held
+    /// briefly and never across a tool call.
+    tools: RwLock<HashMap<String, RegisteredTool>>,
+    /// Like tools, prompts, resources and templates are cloned out of their registries
+    /// before they run.
+    prompts: Mutex<HashMap<String, Arc<dyn McpPrompt>>>,
+    resources: Mutex<HashMap<String, Arc<dyn McpResource>>>,
+    resource_templates: Mutex<Vec<(UriTemplate, Arc<dyn McpResourceTemplate>)>>,
+    sessions: SessionMap,
+    notifications: NotificationDispatcher,
+    progress_interval: Duration,
+    experimental: HashMap<String, serde_json::Value>,
+}
+
+struct RegisteredTool {
+    tool: Arc<dyn McpTool>,
+    /// Disabled tools stay registered but are hidden from clients
+    enabled: bool,
+}
+
+impl RegisteredTool {
+    fn new(tool: Arc<dyn McpTool>) -> Self {
+        Self {
+            tool,
+            enabled: true,
+        }
+    }
 }
 
 impl McpServer {
@@ -19,51 +63,446 @@ impl McpServer {
         Self {
             name: name.to_string(),
             version: version.to_string(),
-            tools: Mutex::new(HashMap::new()),
+            tools: RwLock::new(HashMap::new()),
+            prompts: Mutex::new(HashMap::new()),
+            resources: Mutex::new(HashMap::new()),
+            resource_templates: Mutex::new(Vec::new()),
+            sessions: SessionMap::default(),
+            notifications: NotificationDispatcher::default(),
+            progress_interval: Duration::from_millis(100),
+            experimental: HashMap::new(),
+        }
+    }
+
+    /// Sets the minimum time between `notifications/progress` sent for a single request.
+    /// Faster updates from a tool are dropped so chatty tools can't flood the transport.
+    pub fn with_progress_interval(mut self, progress_interval: Duration) -> Self {
+        self.progress_interval = progress_interval;
+        self
+    }
+
+    /// Advertises a non-standard capability under `capabilities.experimental.<name>` in the
+    /// `initialize` result.
+    pub fn with_experimental_capability(mut self, name: &str, value: serde_json::Value) -> Self {
+        self.experimental.insert(name.to_string(), value);
+        self
+    }
+
+    /// Calls `handler` for every client notification with the given method, after the
+    /// server's own handling of it. Several handlers may be registered for one method.
+    pub fn on_notification<F>(&self, method: &str, handler: F)
+    where
+        F: Fn(Option<&Arc<Session>>, serde_json::Value) + Send + Sync + 'static,
+    {
+        self.notifications.register(method, Arc::new(handler));
+    }
+
+    /// The capabilities sent in the `initialize` result, derived from what is registered.
+    /// Tools are always advertised, since they can be registered at any time and clients
+    /// are told through `notifications/tools/list_changed`. So is logging, with or without
+    /// `logging_layer()`: tools can always log through `RequestContext::log`, and the level
+    /// chosen with `logging/setLevel` applies to both.
+    pub async fn capabilities(&self) -> ServerCapabilities {
+        let has_resources = !self.resources.lock().await.is_empty()
+            || !self.resource_templates.lock().await.is_empty();
+        let has_prompts = !self.prompts.lock().await.is_empty();
+        ServerCapabilities {
+            tools: Some(ToolsCapability {
+                list_changed: Some(true),
+            }),
+            resources: has_resources.then_some(ResourcesCapability {
+                subscribe: Some(true),
+                list_changed: None,
+            }),
+            prompts: has_prompts.then(PromptsCapability::default),
+            logging: Some(LoggingCapability {}),
+            completions: None,
+            experimental: (!self.experimental.is_empty()).then(|| self.experimental.clone()),
         }
     }
 
     pub async fn with_tool(&self, tool: impl McpTool + 'static) -> &Self {
-        let tool_name = tool.name().to_string();
-        info!(tool_name = %tool_name, "Registering tool");
-        self.tools.lock().await.insert(tool_name, Box::new(tool));
+        self.register_tool(tool).await;
         self
     }
 
     pub async fn with_tools(&self, tools: Vec<Box<dyn McpTool>>) -> &Self {
-        let mut tools_lock = self.tools.lock().await;
-        for tool in tools {
-            let name = tool.name().to_string();
-            info!(tool_name = %name, "Registering tool");
-            tools_lock.insert(name, tool);
-        }
+        self.insert_tools(tools);
         self
     }
 
     pub async fn register_tool<T: McpTool + 'static>(&self, tool: T) {
         let tool_name = tool.name().to_string();
         info!(tool_name = %tool_name, "Registering tool");
-        self.tools.lock().await.insert(tool_name, Box::new(tool));
+        self.tools
+            .write()
+            .unwrap()
+            .insert(tool_name, RegisteredTool::new(Arc::new(tool)));
+        self.notify_tools_changed();
     }
 
     pub async fn register_tools<T: HasTools>(&self, provider: T)
     where
         T::Tools: IntoIterator<Item = Box<dyn McpTool>>,
     {
-        let mut tools_lock = self.tools.lock().await;
-        for tool in provider.tools() {
-            let name = tool.name().to_string();
-            info!(tool_name = %name, "Registering tool");
-            tools_lock.insert(name, tool);
+        self.insert_tools(provider.tools());
+    }
+
+    fn insert_tools(&self, tools: impl IntoIterator<Item = Box<dyn McpTool>>) {
+        {
+            let mut tools_lock = self.tools.write().unwrap();
+            for tool in tools {
+                let name = tool.name().to_string();
+                info!(tool_name = %name, "Registering tool");
+                tools_lock.insert(name, RegisteredTool::new(Arc::from(tool)));
+            }
+        }
+        self.notify_tools_changed();
+    }
+
+    /// Removes a tool. Returns `false` if no tool with that name is registered.
+    pub async fn unregister_tool(&self, name: &str) -> bool {
+        let removed = self.tools.write().unwrap().remove(name).is_some();
+        if removed {
+            info!(tool_name = %name, "Unregistered tool");
+            self.notify_tools_changed();
         }
+        removed
+    }
+
+    /// Swaps in a new implementation for the tool registered under the same name, keeping
+    /// its enabled state. Returns `false`, registering nothing, if there is no such tool.
+    pub async fn replace_tool<T: McpTool + 'static>(&self, tool: T) -> bool {
+        let tool_name = tool.name().to_string();
+        let replaced = match self.tools.write().unwrap().get_mut(&tool_name) {
+            Some(registered) => {
+                registered.tool = Arc::new(tool);
+                true
+            }
+            None => false,
+        };
+        if replaced {
+            info!(tool_name = %tool_name, "Replaced tool");
+            self.notify_tools_changed();
+        } else {
+            warn!(tool_name = %tool_name, "No tool to replace");
+        }
+        replaced
+    }
+
+    /// Makes a disabled tool visible and callable again. Returns `false` if no tool with
+    /// that name is registered.
+    pub async fn enable_tool(&self, name: &str) -> bool {
+        self.set_tool_enabled(name, true)
+    }
+
+    /// Hides a tool from `tools/list` and rejects calls to it, without unregistering it.
+    /// Returns `false` if no tool with that name is registered.
+    pub async fn disable_tool(&self, name: &str) -> bool {
+        self.set_tool_enabled(name, false)
+    }
+
+    /// Whether a tool is registered and enabled.
+    pub async fn is_tool_enabled(&self, name: &str) -> bool {
+        self.tools
+            .read()
+            .unwrap()
+            .get(name)
+            .is_some_and(|registered| registered.enabled)
+    }
+
+    fn set_tool_enabled(&self, name: &str, enabled: bool) -> bool {
+        let changed = match self.tools.write().unwrap().get_mut(name) {
+            Some(registered) => {
+                let changed = registered.enabled != enabled;
+                registered.enabled = enabled;
+                changed
+            }
+            None => return false,
+        };
+        if changed {
+            info!(tool_name = %name, enabled = %enabled, "Toggled tool");
+            self.notify_tools_changed();
+        }
+        true
+    }
+
+    /// Sends `notifications/tools/list_changed` to every initialized session.
+    fn notify_tools_changed(&self) {
+        let sessions = self.sessions.read().unwrap();
+        let notified = sessions
+            .values()
+            .filter(|session| session.state() == SessionState::Ready)
+            .filter(|session| {
+                session.notify("notifications/tools/list_changed", serde_json::Value::Null)
+            })
+            .count();
+        debug!(sessions = %notified, "Notified sessions of tool list change");
+    }
+
+    pub async fn register_prompt<P: McpPrompt + 'static>(&self, prompt: P) {
+        let prompt_name = prompt.name().to_string();
+        info!(prompt_name = %prompt_name, "Registering prompt");
+        self.prompts
+            .lock()
+            .await
+            .insert(prompt_name, Arc::new(prompt));
+    }
+
+    pub async fn register_prompts<T: HasPrompts>(&self, provider: T) {
+        let mut prompts_lock = self.prompts.lock().await;
+        for prompt in provider.prompts() {
+            let name = prompt.name().to_string();
+            info!(prompt_name = %name, "Registering prompt");
+            prompts_lock.insert(name, Arc::from(prompt));
+        }
+    }
+
+    pub async fn register_resource<R: McpResource + 'static>(&self, resource: R) {
+        let uri = resource.uri().to_string();
+        info!(uri = %uri, "Registering resource");
+        self.resources.lock().await.insert(uri, Arc::new(resource));
+    }
+
+    pub async fn register_resource_template<T: McpResourceTemplate + 'static>(&self, template: T) {
+        let uri_template = template.uri_template().to_string();
+        info!(uri_template = %uri_template, "Registering resource template");
+        self.resource_templates
+            .lock()
+            .await
+            .push((UriTemplate::parse(&uri_template), Arc::new(template)));
+    }
+
+    /// Registers a new client session. `outbound` receives every server-initiated message
+    /// (notifications and requests) destined for this client.
+    pub fn connect(
+        &self,
+        session_id: String,
+        outbound: mpsc::UnboundedSender<Call>,
+    ) -> Arc<Session> {
+        info!(session_id = %session_id, "Session connected");
+        let session = Arc::new(Session::new(session_id.clone(), outbound));
+        self.sessions
+            .write()
+            .unwrap()
+            .insert(session_id, session.clone());
+        session
+    }
+
+    pub fn disconnect(&self, session_id: &str) {
+        info!(session_id = %session_id, "Session disconnected");
+        if let Some(session) = self.sessions.write().unwrap().remove(session_id) {
+            session.close();
+        }
+    }
+
+    pub fn session(&self, session_id: &str) -> Option<Arc<Session>> {
+        self.sessions.read().unwrap().get(session_id).cloned()
     }
 
+    pub fn resource_notifier(&self) -> ResourceNotifier {
+        ResourceNotifier::new(self.sessions.clone())
+    }
+
+    /// A `tracing_subscriber` layer forwarding tracing events to this server's clients as
+    /// `notifications/message`. Add it to the application's subscriber. Without it clients
+    /// only receive what tools send through `RequestContext::log`.
+    pub fn logging_layer(&self) -> LoggingLayer {
+        LoggingLayer::new(self.sessions.clone())
+    }
+
+    /// Handles a request outside of any session. Session-scoped methods such as
+    /// `resources/subscribe` are rejected. Returns `None` when there is nothing to send
+    /// back, i.e. for notifications.
     pub async fn handle_request(
         &self,
         request: JsonRpcRequest,
-    ) -> Result<JsonRpcResponse, McpError> {
-        let (id, method, params) = match request {
-            JsonRpcRequest::Single(Call::MethodCall(call)) => {
+    ) -> Result<Option<JsonRpcResponse>, McpError> {
+        match request {
+            JsonRpcRequest::Single(call) => self.handle(None, call).await,
+            JsonRpcRequest::Batch(calls) => {
+                Ok(Self::handle_batch(calls, |call| self.handle(None, call)).await)
+            }
+        }
+    }
+
+    /// Handles a request received on `session`, enforcing the initialization lifecycle.
+    /// Batches are rejected once the session has negotiated 2025-06-18 or later.
+    /// The session is available to tools, prompts and resources through
+    /// `Session::current()` while the request is handled. Returns `None` when there is
+    /// nothing to send back, i.e. for notifications.
+    pub async fn handle_session_request(
+        &self,
+        session: &Arc<Session>,
+        request: JsonRpcRequest,
+    ) -> Result<Option<JsonRpcResponse>, McpError> {
+        match request {
+            JsonRpcRequest::Single(call) => self.handle_session_call(session, call).await,
+            // 2025-06-18 removed batching from the protocol
+            JsonRpcRequest::Batch(_)
+                if session.protocol_version().is_some_and(|version| {
+                    protocol_version_at_least(version, PROTOCOL_VERSION_2025_06_18)
+                }) =>
+            {
+                warn!(session_id = %session.id(), "Rejecting batch for protocol without batching");
+                Ok(Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                    jsonrpc: Some(Version::V2),
+                    error: McpError::invalid_request(
+                        "Batches are not supported by the negotiated protocol version",
+                    )
+                    .into(),
+                    id: Id::Null,
+                }))))
+            }
+            JsonRpcRequest::Batch(calls) => {
+                Ok(Self::handle_batch(calls, |call| self.handle_session_call(session, call)).await)
+            }
+        }
+    }
+
+    async fn handle_session_call(
+        &self,
+        session: &Arc<Session>,
+        call: Call,
+    ) -> Result<Option<JsonRpcResponse>, McpError> {
+        match self.track_call(session, &call)? {
+            Some(in_flight) => self.handle_tracked_call(session, call, in_flight).await,
+            None => {
+                session
+                    .clone()
+                    .scope(self.handle(Some(session), call))
+                    .await
+            }
+        }
+    }
+
+    /// Starts tracking `call` so `notifications/cancelled` can find it. Transports that
+    /// queue calls before handling them track them on arrival, so a cancellation sent
+    /// while a call waits is not lost. Returns `None` for calls that are not tracked, and
+    /// fails if the call reuses the id of one still in flight.
+    pub(crate) fn track_call(
+        &self,
+        session: &Arc<Session>,
+        call: &Call,
+    ) -> Result<Option<InFlightRequest>, McpError> {
+        // `initialize` may not be cancelled
+        let Call::MethodCall(method_call) = call else {
+            return Ok(None);
+        };
+        if method_call.method == "initialize" {
+            return Ok(None);
+        }
+        match session.begin_request(&method_call.id) {
+            Some(in_flight) => Ok(Some(in_flight)),
+            None => {
+                warn!(
+                    session_id = %session.id(),
+                    id = ?method_call.id,
+                    "Rejecting request reusing an in-flight id"
+                );
+                Err(McpError::invalid_request(
+                    "Request id is already in use by an in-flight request",
+                ))
+            }
+        }
+    }
+
+    /// Handles a call already tracked by `track_call`, unless it is cancelled first.
+    pub(crate) async fn handle_tracked_call(
+        &self,
+        session: &Arc<Session>,
+        call: Call,
+        in_flight: InFlightRequest,
+    ) -> Result<Option<JsonRpcResponse>, McpError> {
+        let handled = session.clone().scope(self.handle(Some(session), call));
+        tokio::select! {
+            // A response that loses the race to cancellation must not be sent
+            biased;
+            _ = in_flight.token().cancelled() => {
+                info!(session_id = %session.id(), "Request cancelled");
+                Err(McpError::RequestCancelled)
+            }
+            response = handled => response,
+        }
+    }
+
+    /// Handles a client's response to a request the server sent on `session`, such as one
+    /// issued by a tool through its `RequestContext`.
+    pub fn handle_session_response(&self, session: &Session, response: Output) {
+        debug!(session_id = %session.id(), id = ?response.id(), "Received JSON-RPC response");
+        session.complete_request(response);
+    }
+
+    /// Runs the calls of a batch concurrently. The result holds one response per method
+    /// call, in no particular order; notifications and cancelled calls get none, and a
+    /// batch left with no responses gets no reply at all.
+    async fn handle_batch<F>(
+        calls: Vec<Call>,
+        handle: impl Fn(Call) -> F,
+    ) -> Option<JsonRpcResponse>
+    where
+        F: Future<Output = Result<Option<JsonRpcResponse>, McpError>>,
+    {
+        if calls.is_empty() {
+            warn!("Received empty batch");
+            return Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                jsonrpc: Some(Version::V2),
+                error: McpError::invalid_request("Empty batch").into(),
+                id: Id::Null,
+            })));
+        }
+        debug!(size = %calls.len(), "Received JSON-RPC batch");
+
+        let handled = calls.into_iter().map(|call| {
+            let id = match &call {
+                Call::MethodCall(method_call) => Some(method_call.id.clone()),
+                Call::Invalid { id } => Some(id.clone()),
+                Call::Notification(_) => None,
+            };
+            // `initialize` must be sent on its own
+            let response = match &call {
+                Call::MethodCall(method_call) if method_call.method == "initialize" => None,
+                _ => Some(handle(call)),
+            };
+            async move {
+                let response = match response {
+                    Some(response) => response.await,
+                    None => Err(McpError::invalid_request("initialize cannot be batched")),
+                };
+                (id, response)
+            }
+        });
+        let outputs: Vec<Output> = join_all(handled)
+            .await
+            .into_iter()
+            .filter_map(|(id, response)| match response {
+                Ok(Some(JsonRpcResponse::Single(output))) => Some(output),
+                Ok(Some(JsonRpcResponse::Batch(outputs))) => {
+                    warn!(size = %outputs.len(), "Dropping nested batch response");
+                    None
+                }
+                Ok(None) => None,
+                Err(McpError::RequestCancelled) => None,
+                Err(e) => id.map(|id| {
+                    Output::Failure(Failure {
+                        jsonrpc: Some(Version::V2),
+                        error: e.into(),
+                        id,
+                    })
+                }),
+            })
+            .collect();
+        (!outputs.is_empty()).then_some(JsonRpcResponse::Batch(outputs))
+    }
+
+    /// Handles a single call. Notifications have no response.
+    async fn handle(
+        &self,
+        session: Option<&Arc<Session>>,
+        call: Call,
+    ) -> Result<Option<JsonRpcResponse>, McpError> {
+        let (id, method, params) = match call {
+            Call::MethodCall(call) => {
                 debug!(
                     method = %call.method,
                     id = ?call.id,
@@ -72,40 +511,93 @@ impl McpServer {
                 );
                 (call.id, call.method, call.params)
             }
-            JsonRpcRequest::Single(Call::Notification(notification)) => {
+            Call::Notification(notification) => {
                 debug!(
                     method = %notification.method,
                     params = %serde_json::to_string_pretty(&notification.params).unwrap_or_default(),
                     "Received JSON-RPC notification"
                 );
-                // For now, just return an empty success response
-                // TODO
-                return Ok(JsonRpcResponse::Single(Output::Success(Success {
-                    jsonrpc: Some(Version::V2),
-                    result: serde_json::json!({}),
-                    id: Id::Num(0),
-                })));
+                self.notifications.dispatch(session, notification);
+                return Ok(None);
             }
-            _ => {
-                return Ok(JsonRpcResponse::Single(Output::Failure(Failure {
+            Call::Invalid { id } => {
+                warn!(id = ?id, "Received invalid JSON-RPC call");
+                return Ok(Some(JsonRpcResponse::Single(Output::Failure(Failure {
                     jsonrpc: Some(Version::V2),
-                    error: McpError::InvalidRequest.into(),
-                    id: Id::Num(0),
-                })));
+                    error: McpError::invalid_request("Invalid JSON-RPC call").into(),
+                    id,
+                }))));
             }
         };
 
+        // Requests outside a session, or before `initialize`, get the newest behavior
+        let negotiated_version = session
+            .and_then(|session| session.protocol_version())
+            .unwrap_or(LATEST_PROTOCOL_VERSION);
+
+        // Until `initialize` has been answered only pings are allowed
+        if let Some(session) = session {
+            if session.state() == SessionState::Uninitialized
+                && !matches!(method.as_str(), "initialize" | "ping")
+            {
+                warn!(
+                    session_id = %session.id(),
+                    method = %method,
+                    "Rejecting request before initialize"
+                );
+                return Ok(Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                    jsonrpc: Some(Version::V2),
+                    error: McpError::invalid_request(format!(
+                        "Session not initialized, cannot handle {}",
+                        method
+                    ))
+                    .into(),
+                    id,
+                }))));
+            }
+        }
+
         let response = match method.as_str() {
             "initialize" => {
                 info!("Processing initialize request");
-                let tools_lock = self.tools.lock().await;
-                let capabilities = ServerCapabilities {
-                    tools: tools_lock.keys().map(|k| (k.clone(), true)).collect(),
+                let params = match params {
+                    Params::Map(map) => map,
+                    _ => Default::default(),
                 };
+                let requested_version = params.get("protocolVersion").and_then(|v| v.as_str());
+                let protocol_version = requested_version
+                    .map(negotiate_protocol_version)
+                    .unwrap_or(LATEST_PROTOCOL_VERSION);
 
+                if let Some(session) = session {
+                    let client_info: Option<Implementation> = params
+                        .get("clientInfo")
+                        .and_then(|info| serde_json::from_value(info.clone()).ok());
+                    let client_capabilities: Option<ClientCapabilities> =
+                        params.get("capabilities").and_then(|capabilities| {
+                            serde_json::from_value(capabilities.clone())
+                                .inspect_err(
+                                    |e| warn!(error = %e, "Failed to parse client capabilities"),
+                                )
+                                .ok()
+                        });
+                    info!(
+                        session_id = %session.id(),
+                        client = ?client_info,
+                        "Initializing session"
+                    );
+                    if !session.initialize(protocol_version, client_info, client_capabilities) {
+                        warn!(session_id = %session.id(), "Session already initialized");
+                        return Ok(Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: McpError::invalid_request("Session already initialized").into(),
+                            id,
+                        }))));
+                    }
+                }
                 let result = InitializeResult {
-                    protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-                    capabilities,
+                    protocol_version: protocol_version.to_string(),
+                    capabilities: self.capabilities().await,
                     server_info: ServerInfo {
                         name: self.name.clone(),
                         version: self.version.clone(),
@@ -116,8 +608,9 @@ impl McpServer {
                 debug!(
                     server_name = %self.name,
                     server_version = %self.version,
-                    protocol_version = %LATEST_PROTOCOL_VERSION,
-                    num_tools = %tools_lock.len(),
+                    requested_version = ?requested_version,
+                    protocol_version = %protocol_version,
+                    capabilities = ?result.capabilities,
                     "Sending initialize response"
                 );
 
@@ -127,15 +620,42 @@ impl McpServer {
                     id,
                 }))
             }
+            "ping" => JsonRpcResponse::Single(Output::Success(Success {
+                jsonrpc: Some(Version::V2),
+                result: serde_json::json!({}),
+                id,
+            })),
             "tools/list" => {
                 info!("Processing tools/list request");
-                let tools_lock = self.tools.lock().await;
-                let tools: Vec<Tool> = tools_lock
+                let tools: Vec<Tool> = self
+                    .tools
+                    .read()
+                    .unwrap()
                     .values()
-                    .map(|tool| Tool {
-                        name: tool.name().to_string(),
-                        description: tool.description().to_string(),
-                        input_schema: tool.input_schema(),
+                    .filter(|registered| registered.enabled)
+                    .map(|RegisteredTool { tool, .. }| {
+                        let mut listed = Tool {
+                            name: tool.name().to_string(),
+                            description: tool.description().to_string(),
+                            input_schema: tool.input_schema(),
+                            title: None,
+                            output_schema: None,
+                            annotations: None,
+                        };
+                        if protocol_version_at_least(
+                            negotiated_version,
+                            PROTOCOL_VERSION_2025_03_26,
+                        ) {
+                            listed.annotations = tool.annotations();
+                        }
+                        if protocol_version_at_least(
+                            negotiated_version,
+                            PROTOCOL_VERSION_2025_06_18,
+                        ) {
+                            listed.title = tool.title().map(str::to_string);
+                            listed.output_schema = tool.output_schema();
+                        }
+                        listed
                     })
                     .collect();
 
@@ -158,19 +678,7 @@ impl McpServer {
             }
             "tools/call" => {
                 info!("Processing tools/call request");
-                let params = match params {
-                    Params::Map(map) => map,
-                    _ => {
-                        warn!("Invalid params format for tools/call - expected Map");
-                        return Err(McpError::InvalidParams);
-                    }
-                };
-
-                let request: CallToolRequest =
-                    serde_json::from_value(serde_json::Value::Object(params)).map_err(|_| {
-                        warn!("Failed to parse tool call request parameters");
-                        McpError::InvalidParams
-                    })?;
+                let request: CallToolRequest = parse_params(&method, params)?;
 
                 debug!(
                     tool = %request.name,
@@ -178,10 +686,17 @@ impl McpServer {
                     "Executing tool"
                 );
 
-                let tools_lock = self.tools.lock().await;
-                let tool = tools_lock.get(&request.name).ok_or_else(|| {
+                let tool = self
+                    .tools
+                    .read()
+                    .unwrap()
+                    .get(&request.name)
+                    .filter(|registered| registered.enabled)
+                    .map(|registered| registered.tool.clone());
+                let tool = tool.ok_or_else(|| {
                     warn!(tool = %request.name, "Tool not found");
-                    McpError::MethodNotFound
+                    McpError::invalid_params(format!("Unknown tool: {}", request.name))
+                        .with_data(serde_json::json!({ "tool": request.name }))
                 })?;
 
                 let args = match request.arguments {
@@ -195,7 +710,23 @@ impl McpServer {
                     "Executing tool with arguments"
                 );
 
-                match tool.execute(args).await {
+                let mut ctx = RequestContext::new(
+                    id.clone(),
+                    session.map(|session| session.clone() as Arc<dyn McpPeer>),
+                );
+                if let Some(token) = session.and_then(|session| session.cancellation_token(&id)) {
+                    ctx = ctx.with_cancellation(token);
+                }
+                if let Some(token) = request.meta.and_then(|meta| meta.progress_token) {
+                    ctx = ctx.with_progress(ProgressReporter::new(token, self.progress_interval));
+                }
+                let result = tool.execute(args, ctx).await.or_else(|e| {
+                    if !e.is_protocol_error() {
+                        warn!(tool = %request.name, error = %e, "Tool execution failed");
+                    }
+                    e.into_result()
+                });
+                match result {
                     Ok(result) => {
                         let content = result
                             .content
@@ -203,16 +734,24 @@ impl McpServer {
                             .map(|c| Content::Text { text: c.text })
                             .collect();
 
+                        let structured_content = result.structured_content.filter(|_| {
+                            protocol_version_at_least(
+                                negotiated_version,
+                                PROTOCOL_VERSION_2025_06_18,
+                            )
+                        });
+
                         let result = CallToolResult {
                             content,
                             is_error: Some(result.is_error),
+                            structured_content,
                         };
 
                         debug!(
                             tool = %request.name,
                             is_error = ?result.is_error,
                             content_length = %result.content.len(),
-                            "Tool execution successful"
+                            "Tool execution finished"
                         );
 
                         JsonRpcResponse::Single(Output::Success(Success {
@@ -222,14 +761,273 @@ impl McpServer {
                         }))
                     }
                     Err(e) => {
-                        warn!(
-                            tool = %request.name,
-                            error = %e,
-                            "Tool execution failed"
+                        warn!(tool = %request.name, error = %e, "Rejected tool arguments");
+                        JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: McpError::invalid_params(e.to_string())
+                                .with_data(serde_json::json!({ "tool": request.name }))
+                                .into(),
+                            id,
+                        }))
+                    }
+                }
+            }
+            "resources/list" => {
+                info!("Processing resources/list request");
+                let resources_lock = self.resources.lock().await;
+                let resources: Vec<Resource> = resources_lock
+                    .values()
+                    .map(|resource| Resource {
+                        uri: resource.uri().to_string(),
+                        name: resource.name().to_string(),
+                        description: resource.description().map(str::to_string),
+                        mime_type: resource.mime_type().map(str::to_string),
+                    })
+                    .collect();
+
+                let result = ListResourcesResult {
+                    resources,
+                    next_cursor: None,
+                };
+
+                debug!(
+                    num_resources = %result.resources.len(),
+                    "Sending resources list response"
+                );
+
+                JsonRpcResponse::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result: serde_json::to_value(result)?,
+                    id,
+                }))
+            }
+            "resources/templates/list" => {
+                info!("Processing resources/templates/list request");
+                let templates_lock = self.resource_templates.lock().await;
+                let resource_templates: Vec<ResourceTemplate> = templates_lock
+                    .iter()
+                    .map(|(_, template)| ResourceTemplate {
+                        uri_template: template.uri_template().to_string(),
+                        name: template.name().to_string(),
+                        description: template.description().map(str::to_string),
+                        mime_type: template.mime_type().map(str::to_string),
+                    })
+                    .collect();
+
+                let result = ListResourceTemplatesResult {
+                    resource_templates,
+                    next_cursor: None,
+                };
+
+                debug!(
+                    num_templates = %result.resource_templates.len(),
+                    "Sending resource templates list response"
+                );
+
+                JsonRpcResponse::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result: serde_json::to_value(result)?,
+                    id,
+                }))
+            }
+            "resources/read" => {
+                info!("Processing resources/read request");
+                let request: ReadResourceRequest = parse_params(&method, params)?;
+
+                match self.read_resource(&request.uri).await {
+                    Some(Ok(contents)) => {
+                        debug!(
+                            uri = %request.uri,
+                            num_contents = %contents.len(),
+                            "Resource read successful"
+                        );
+                        JsonRpcResponse::Single(Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result: serde_json::to_value(ReadResourceResult { contents })?,
+                            id,
+                        }))
+                    }
+                    Some(Err(e)) => {
+                        warn!(uri = %request.uri, error = %e, "Resource read failed");
+                        JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: McpError::execution_failed(e)
+                                .with_data(serde_json::json!({ "uri": request.uri }))
+                                .into(),
+                            id,
+                        }))
+                    }
+                    None => {
+                        warn!(uri = %request.uri, "Resource not found");
+                        JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: McpError::resource_not_found(&request.uri).into(),
+                            id,
+                        }))
+                    }
+                }
+            }
+            "prompts/list" => {
+                info!("Processing prompts/list request");
+                let prompts_lock = self.prompts.lock().await;
+                let prompts: Vec<Prompt> = prompts_lock
+                    .values()
+                    .map(|prompt| {
+                        let arguments = prompt.arguments();
+                        Prompt {
+                            name: prompt.name().to_string(),
+                            description: Some(prompt.description().to_string())
+                                .filter(|d| !d.is_empty()),
+                            arguments: (!arguments.is_empty()).then_some(arguments),
+                        }
+                    })
+                    .collect();
+
+                let result = ListPromptsResult {
+                    prompts,
+                    next_cursor: None,
+                };
+
+                debug!(
+                    num_prompts = %result.prompts.len(),
+                    prompt_names = ?result.prompts.iter().map(|p| &p.name).collect::<Vec<_>>(),
+                    "Sending prompts list response"
+                );
+
+                JsonRpcResponse::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result: serde_json::to_value(result)?,
+                    id,
+                }))
+            }
+            "prompts/get" => {
+                info!("Processing prompts/get request");
+                let request: GetPromptRequest = parse_params(&method, params)?;
+
+                let prompt = self.prompts.lock().await.get(&request.name).cloned();
+                let Some(prompt) = prompt else {
+                    warn!(prompt = %request.name, "Prompt not found");
+                    return Ok(Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                        jsonrpc: Some(Version::V2),
+                        error: McpError::invalid_params(format!(
+                            "Unknown prompt: {}",
+                            request.name
+                        ))
+                        .with_data(serde_json::json!({ "prompt": request.name }))
+                        .into(),
+                        id,
+                    }))));
+                };
+
+                let args = request.arguments.unwrap_or_default();
+                let missing: Vec<String> = prompt
+                    .arguments()
+                    .into_iter()
+                    .filter(|arg| arg.required == Some(true) && !args.contains_key(&arg.name))
+                    .map(|arg| arg.name)
+                    .collect();
+                if !missing.is_empty() {
+                    warn!(prompt = %request.name, missing = ?missing, "Missing required prompt arguments");
+                    return Ok(Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                        jsonrpc: Some(Version::V2),
+                        error: McpError::invalid_params(format!(
+                            "Missing required arguments: {}",
+                            missing.join(", ")
+                        ))
+                        .with_data(serde_json::json!({ "missing": missing }))
+                        .into(),
+                        id,
+                    }))));
+                }
+
+                match prompt.get(args).await {
+                    Ok(messages) => {
+                        let result = GetPromptResult {
+                            description: Some(prompt.description().to_string())
+                                .filter(|d| !d.is_empty()),
+                            messages,
+                        };
+
+                        debug!(
+                            prompt = %request.name,
+                            num_messages = %result.messages.len(),
+                            "Prompt rendered successfully"
+                        );
+
+                        JsonRpcResponse::Single(Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result: serde_json::to_value(result)?,
+                            id,
+                        }))
+                    }
+                    Err(e) => {
+                        warn!(prompt = %request.name, error = %e, "Prompt rendering failed");
+                        JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: McpError::execution_failed(e)
+                                .with_data(serde_json::json!({ "prompt": request.name }))
+                                .into(),
+                            id,
+                        }))
+                    }
+                }
+            }
+            "resources/subscribe" | "resources/unsubscribe" => {
+                info!(method = %method, "Processing resource subscription request");
+                let request: SubscribeRequest = parse_params(&method, params)?;
+
+                match session {
+                    Some(session) => {
+                        if method == "resources/subscribe" {
+                            session.subscribe(&request.uri);
+                        } else {
+                            session.unsubscribe(&request.uri);
+                        }
+                        debug!(
+                            session_id = %session.id(),
+                            uri = %request.uri,
+                            method = %method,
+                            "Updated resource subscriptions"
                         );
+                        JsonRpcResponse::Single(Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result: serde_json::json!({}),
+                            id,
+                        }))
+                    }
+                    None => {
+                        warn!(method = %method, "Subscription requested outside of a session");
                         JsonRpcResponse::Single(Output::Failure(Failure {
                             jsonrpc: Some(Version::V2),
-                            error: JsonRpcError::new(ErrorCode::ServerError(-32000)),
+                            error: McpError::invalid_request("Subscriptions require a session")
+                                .into(),
+                            id,
+                        }))
+                    }
+                }
+            }
+            "logging/setLevel" => {
+                let request: SetLevelRequest = parse_params(&method, params)?;
+
+                match session {
+                    Some(session) => {
+                        info!(
+                            session_id = %session.id(),
+                            level = ?request.level,
+                            "Client set log level"
+                        );
+                        session.set_log_level(request.level);
+                        JsonRpcResponse::Single(Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result: serde_json::json!({}),
+                            id,
+                        }))
+                    }
+                    None => {
+                        warn!("Log level set outside of a session");
+                        JsonRpcResponse::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: McpError::invalid_request("Logging requires a session").into(),
                             id,
                         }))
                     }
@@ -239,7 +1037,7 @@ impl McpServer {
                 warn!(method = %method, "Unknown method called");
                 JsonRpcResponse::Single(Output::Failure(Failure {
                     jsonrpc: Some(Version::V2),
-                    error: JsonRpcError::method_not_found(),
+                    error: McpError::method_not_found(&method).into(),
                     id,
                 }))
             }
@@ -252,20 +1050,54 @@ impl McpServer {
             "Full JSON response"
         );
 
-        Ok(response)
+        Ok(Some(response))
     }
 
-    pub fn handle_notification(
-        &mut self,
-        method: &str,
-        _params: Option<serde_json::Value>,
-    ) -> Result<(), McpError> {
-        match method {
-            "notifications/initialized" => {
-                info!("Client completed initialization");
-                Ok(())
-            }
-            _ => Err(McpError::MethodNotFound),
+    /// Reads `uri` from an exactly matching resource, falling back to the first matching
+    /// template. Returns `None` if nothing registered serves the URI.
+    async fn read_resource(&self, uri: &str) -> Option<Result<Vec<ResourceContents>, String>> {
+        let resource = self.resources.lock().await.get(uri).cloned();
+        if let Some(resource) = resource {
+            return Some(resource.read().await);
         }
+
+        let matched =
+            self.resource_templates
+                .lock()
+                .await
+                .iter()
+                .find_map(|(template, handler)| {
+                    template
+                        .matches(uri)
+                        .map(|variables| (handler.clone(), variables))
+                });
+        let (handler, variables) = matched?;
+        debug!(
+            uri = %uri,
+            uri_template = %handler.uri_template(),
+            "Resolved resource through template"
+        );
+        Some(handler.read(uri, variables).await)
     }
 }
+
+/// Parses the params of `method` into its request type. Failures name the offending field
+/// in the error's `data.path`.
+fn parse_params<T: DeserializeOwned>(method: &str, params: Params) -> Result<T, McpError> {
+    let params = match params {
+        Params::Map(map) => serde_json::Value::Object(map),
+        _ => {
+            warn!(method = %method, "Invalid params format - expected Map");
+            return Err(McpError::invalid_params(format!(
+                "Params for {} must be an object",
+                method
+            )));
+        }
+    };
+    serde_path_to_error::deserialize(params).map_err(|e| {
+        let path = e.path().to_string();
+        warn!(method = %method, path = %path, error = %e.inner(), "Failed to parse parameters");
+        McpError::invalid_params(format!("Invalid params for {}: {}", method, e.inner()))
+            .with_data(serde_json::json!({ "path": path }))
+    })
+}
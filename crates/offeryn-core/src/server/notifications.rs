@@ -0,0 +1,97 @@
+use super::Session;
+use jsonrpc_core::{Notification, Params};
+use offeryn_types::CancelledNotificationParams;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info, warn};
+
+/// Application callback for a client notification. Receives the session the notification
+/// arrived on, if any, and its params (`null` if it had none).
+pub type NotificationHandler = Arc<dyn Fn(Option<&Arc<Session>>, Value) + Send + Sync>;
+
+/// Routes client notifications to the server's own handling and to the handlers the
+/// application registered through `McpServer::on_notification`.
+#[derive(Default)]
+pub(crate) struct NotificationDispatcher {
+    handlers: RwLock<HashMap<String, Vec<NotificationHandler>>>,
+}
+
+impl NotificationDispatcher {
+    pub(crate) fn register(&self, method: &str, handler: NotificationHandler) {
+        self.handlers
+            .write()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push(handler);
+    }
+
+    pub(crate) fn dispatch(&self, session: Option<&Arc<Session>>, notification: Notification) {
+        let Notification { method, params, .. } = notification;
+        let builtin =
+            session.is_some_and(|session| Self::handle_builtin(session, &method, &params));
+
+        // Cloned so handlers may register further handlers
+        let handlers = self
+            .handlers
+            .read()
+            .unwrap()
+            .get(&method)
+            .cloned()
+            .unwrap_or_default();
+        if handlers.is_empty() {
+            if !builtin {
+                debug!(method = %method, "Ignoring notification");
+            }
+            return;
+        }
+        let params = match params {
+            Params::Map(map) => Value::Object(map),
+            Params::Array(values) => Value::Array(values),
+            Params::None => Value::Null,
+        };
+        for handler in handlers {
+            handler(session, params.clone());
+        }
+    }
+
+    /// Handles the notifications the protocol defines. Returns `false` for any other method.
+    fn handle_builtin(session: &Session, method: &str, params: &Params) -> bool {
+        match method {
+            "notifications/cancelled" => {
+                let params: CancelledNotificationParams = match params.clone().parse() {
+                    Ok(params) => params,
+                    Err(e) => {
+                        warn!(error = %e.message, "Invalid cancellation notification");
+                        return true;
+                    }
+                };
+                info!(
+                    session_id = %session.id(),
+                    request_id = ?params.request_id,
+                    reason = ?params.reason,
+                    "Client cancelled request"
+                );
+                // Requests that already finished, or never existed, are ignored
+                session.cancel_request(&params.request_id);
+            }
+            "notifications/initialized" => {
+                if session.mark_ready() {
+                    info!(session_id = %session.id(), "Client completed initialization");
+                } else {
+                    warn!(
+                        session_id = %session.id(),
+                        "Received initialized notification before initialize"
+                    );
+                }
+            }
+            "notifications/roots/list_changed" => {
+                info!(session_id = %session.id(), "Client roots changed");
+                session.invalidate_roots();
+            }
+            _ => return false,
+        }
+        true
+    }
+}
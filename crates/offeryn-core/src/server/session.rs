@@ -0,0 +1,495 @@
+use async_trait::async_trait;
+use jsonrpc_core::{Call, Id, MethodCall, Notification, Output, Params, Version};
+use offeryn_types::{
+    CancellationToken, ClientCapabilities, CreateMessageRequest, CreateMessageResult,
+    ElicitRequest, ElicitResult, Implementation, ListRootsResult, LoggingLevel,
+    LoggingMessageParams, McpPeer, PeerError, Root,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+pub(crate) type SessionMap = Arc<RwLock<HashMap<String, Arc<Session>>>>;
+
+tokio::task_local! {
+    static CURRENT_SESSION: Arc<Session>;
+}
+
+/// Where a session is in the MCP lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Connected, waiting for the client's `initialize` request
+    Uninitialized,
+    /// `initialize` has been answered, waiting for `notifications/initialized`
+    Initializing,
+    /// The client has confirmed initialization
+    Ready,
+}
+
+/// What the client told us about itself in `initialize`.
+#[derive(Default)]
+struct ClientDetails {
+    info: Option<Implementation>,
+    capabilities: Option<ClientCapabilities>,
+}
+
+/// The client's roots as of its last `roots/list` answer.
+#[derive(Default)]
+struct RootsCache {
+    roots: Option<Vec<Root>>,
+    /// Bumped on every `notifications/roots/list_changed`, so an answer that raced with a
+    /// change is not cached
+    generation: u64,
+}
+
+/// A single client connection to an `McpServer`.
+///
+/// Sessions are created by transports through `McpServer::connect` and own the channel
+/// used to push server-initiated messages back to the client.
+pub struct Session {
+    id: String,
+    outbound: mpsc::UnboundedSender<Call>,
+    subscriptions: Mutex<HashSet<String>>,
+    protocol_version: RwLock<Option<&'static str>>,
+    state: RwLock<SessionState>,
+    client: RwLock<ClientDetails>,
+    /// Server-to-client requests awaiting a response, keyed by JSON-RPC id
+    pending: Mutex<HashMap<u64, oneshot::Sender<Output>>>,
+    next_request_id: AtomicU64,
+    /// Client requests being handled, so `notifications/cancelled` can stop them
+    in_flight: Mutex<HashMap<Id, CancellationToken>>,
+    roots: Mutex<RootsCache>,
+    log_level: RwLock<LoggingLevel>,
+}
+
+impl Session {
+    pub(crate) fn new(id: String, outbound: mpsc::UnboundedSender<Call>) -> Self {
+        Self {
+            id,
+            outbound,
+            subscriptions: Mutex::new(HashSet::new()),
+            protocol_version: RwLock::new(None),
+            state: RwLock::new(SessionState::Uninitialized),
+            client: RwLock::new(ClientDetails::default()),
+            pending: Mutex::new(HashMap::new()),
+            next_request_id: AtomicU64::new(1),
+            in_flight: Mutex::new(HashMap::new()),
+            roots: Mutex::new(RootsCache::default()),
+            log_level: RwLock::new(LoggingLevel::Info),
+        }
+    }
+
+    /// The session whose request the current task is handling, if any. Tools and prompts
+    /// can use this to find out which client is calling them.
+    pub fn current() -> Option<Arc<Session>> {
+        CURRENT_SESSION.try_with(Arc::clone).ok()
+    }
+
+    /// Runs `future` with this session as `Session::current()`.
+    pub(crate) async fn scope<F: Future>(self: Arc<Self>, future: F) -> F::Output {
+        CURRENT_SESSION.scope(self, future).await
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The protocol version negotiated during `initialize`, if it has happened yet.
+    pub fn protocol_version(&self) -> Option<&'static str> {
+        *self.protocol_version.read().unwrap()
+    }
+
+    pub fn state(&self) -> SessionState {
+        *self.state.read().unwrap()
+    }
+
+    /// The client's name and version from `initialize`.
+    pub fn client_info(&self) -> Option<Implementation> {
+        self.client.read().unwrap().info.clone()
+    }
+
+    /// The capabilities the client declared in `initialize`.
+    pub fn client_capabilities(&self) -> Option<ClientCapabilities> {
+        self.client.read().unwrap().capabilities.clone()
+    }
+
+    /// Records the outcome of `initialize`. Returns `false`, changing nothing, if the
+    /// session was already initialized.
+    pub(crate) fn initialize(
+        &self,
+        protocol_version: &'static str,
+        info: Option<Implementation>,
+        capabilities: Option<ClientCapabilities>,
+    ) -> bool {
+        let mut state = self.state.write().unwrap();
+        if *state != SessionState::Uninitialized {
+            return false;
+        }
+        *state = SessionState::Initializing;
+        *self.protocol_version.write().unwrap() = Some(protocol_version);
+        *self.client.write().unwrap() = ClientDetails { info, capabilities };
+        true
+    }
+
+    /// Handles `notifications/initialized`. Returns `false` if `initialize` has not been
+    /// answered yet.
+    pub(crate) fn mark_ready(&self) -> bool {
+        let mut state = self.state.write().unwrap();
+        match *state {
+            SessionState::Uninitialized => false,
+            SessionState::Initializing | SessionState::Ready => {
+                *state = SessionState::Ready;
+                true
+            }
+        }
+    }
+
+    /// Sends a JSON-RPC notification to the client. Returns `false` if the transport has
+    /// already gone away.
+    pub fn notify(&self, method: &str, params: Value) -> bool {
+        let params = match params {
+            Value::Object(map) => Params::Map(map),
+            Value::Array(values) => Params::Array(values),
+            _ => Params::None,
+        };
+        debug!(session_id = %self.id, method = %method, "Sending notification");
+        self.outbound
+            .send(Call::Notification(Notification {
+                jsonrpc: Some(Version::V2),
+                method: method.to_string(),
+                params,
+            }))
+            .is_ok()
+    }
+
+    /// The least severe log level the client wants to receive. Until it sends
+    /// `logging/setLevel` this is `Info`.
+    pub fn log_level(&self) -> LoggingLevel {
+        *self.log_level.read().unwrap()
+    }
+
+    pub(crate) fn set_log_level(&self, level: LoggingLevel) {
+        *self.log_level.write().unwrap() = level;
+    }
+
+    /// Sends a `notifications/message` log entry, unless it is less severe than the
+    /// client's log level. Returns whether it was sent.
+    pub fn log(&self, level: LoggingLevel, logger: Option<&str>, data: Value) -> bool {
+        if level < self.log_level() {
+            return false;
+        }
+        let params = LoggingMessageParams {
+            level,
+            logger: logger.map(str::to_string),
+            data,
+        };
+        match serde_json::to_value(params) {
+            Ok(params) => self.notify("notifications/message", params),
+            Err(_) => false,
+        }
+    }
+
+    /// Sends a JSON-RPC request to the client and waits for its response, which the
+    /// transport hands back through `McpServer::handle_session_response`.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, PeerError> {
+        let id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        // Forget the request if the caller stops waiting for it
+        let _pending = PendingRequest { session: self, id };
+
+        let params = match params {
+            Value::Object(map) => Params::Map(map),
+            Value::Array(values) => Params::Array(values),
+            _ => Params::None,
+        };
+        debug!(session_id = %self.id, method = %method, id = %id, "Sending request");
+        self.outbound
+            .send(Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: method.to_string(),
+                params,
+                id: Id::Num(id),
+            }))
+            .map_err(|_| PeerError::Disconnected)?;
+
+        match rx.await.map_err(|_| PeerError::Disconnected)? {
+            Output::Success(success) => Ok(success.result),
+            Output::Failure(failure) => Err(PeerError::Rpc(failure.error)),
+        }
+    }
+
+    /// The roots the client allows the server to operate on, from `roots/list`.
+    ///
+    /// Clients that declare `roots.listChanged` are asked once and the answer is cached
+    /// until they send `notifications/roots/list_changed`; other clients are asked on
+    /// every call.
+    pub async fn roots(&self) -> Result<Vec<Root>, PeerError> {
+        let capability = self
+            .client_capabilities()
+            .and_then(|capabilities| capabilities.roots)
+            .ok_or(PeerError::Unsupported("roots"))?;
+        let generation = {
+            let cache = self.roots.lock().unwrap();
+            if let Some(roots) = &cache.roots {
+                return Ok(roots.clone());
+            }
+            cache.generation
+        };
+
+        let roots = self
+            .typed_request::<ListRootsResult>("roots/list", Value::Null)
+            .await?
+            .roots;
+        if capability.list_changed == Some(true) {
+            let mut cache = self.roots.lock().unwrap();
+            if cache.generation == generation {
+                cache.roots = Some(roots.clone());
+            }
+        }
+        Ok(roots)
+    }
+
+    /// Asks the client to sample a message from its model with `sampling/createMessage`.
+    /// The client usually lets its user review the request first, so this may take a
+    /// while. Fails with `PeerError::Unsupported` if the client did not declare the
+    /// `sampling` capability.
+    pub async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<CreateMessageResult, PeerError> {
+        if self
+            .client_capabilities()
+            .and_then(|capabilities| capabilities.sampling)
+            .is_none()
+        {
+            return Err(PeerError::Unsupported("sampling"));
+        }
+        let params =
+            serde_json::to_value(request).map_err(|e| PeerError::InvalidRequest(e.to_string()))?;
+        self.typed_request("sampling/createMessage", params).await
+    }
+
+    /// Asks the user for structured input with `elicitation/create`. Fails with
+    /// `PeerError::Unsupported` if the client did not declare the `elicitation` capability.
+    pub async fn elicit(&self, request: ElicitRequest) -> Result<ElicitResult, PeerError> {
+        if self
+            .client_capabilities()
+            .and_then(|capabilities| capabilities.elicitation)
+            .is_none()
+        {
+            return Err(PeerError::Unsupported("elicitation"));
+        }
+        let params =
+            serde_json::to_value(request).map_err(|e| PeerError::InvalidRequest(e.to_string()))?;
+        self.typed_request("elicitation/create", params).await
+    }
+
+    /// `request`, parsing the result as `T`.
+    async fn typed_request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, PeerError> {
+        let result = self.request(method, params).await?;
+        serde_json::from_value(result).map_err(|e| PeerError::InvalidResponse(e.to_string()))
+    }
+
+    /// Fails a server-to-client request the transport had no way to deliver with
+    /// `PeerError::Disconnected`, instead of leaving it waiting for an answer.
+    pub(crate) fn abandon_request(&self, id: &Id) {
+        if let Id::Num(id) = id {
+            if self.pending.lock().unwrap().remove(id).is_some() {
+                warn!(session_id = %self.id, id = %id, "Abandoned undeliverable request");
+            }
+        }
+    }
+
+    /// Forgets the cached roots after `notifications/roots/list_changed`.
+    pub(crate) fn invalidate_roots(&self) {
+        let mut cache = self.roots.lock().unwrap();
+        cache.roots = None;
+        cache.generation += 1;
+    }
+
+    /// Delivers a client response to the `request` waiting for it. Returns `false` if no
+    /// request with that id is outstanding.
+    pub(crate) fn complete_request(&self, output: Output) -> bool {
+        let Id::Num(id) = output.id() else {
+            warn!(session_id = %self.id, id = ?output.id(), "Response with unexpected id");
+            return false;
+        };
+        match self.pending.lock().unwrap().remove(id) {
+            Some(tx) => tx.send(output).is_ok(),
+            None => {
+                warn!(session_id = %self.id, id = %id, "Response for unknown request");
+                false
+            }
+        }
+    }
+
+    /// Starts tracking a client request. The request stays cancellable until the returned
+    /// guard is dropped. Returns `None` if a request with the same id is still in flight.
+    pub(crate) fn begin_request(self: &Arc<Self>, id: &Id) -> Option<InFlightRequest> {
+        let token = CancellationToken::new();
+        match self.in_flight.lock().unwrap().entry(id.clone()) {
+            Entry::Occupied(_) => return None,
+            Entry::Vacant(entry) => {
+                entry.insert(token.clone());
+            }
+        }
+        Some(InFlightRequest {
+            session: self.clone(),
+            id: id.clone(),
+            token,
+        })
+    }
+
+    /// The cancellation token of an in-flight client request.
+    pub(crate) fn cancellation_token(&self, id: &Id) -> Option<CancellationToken> {
+        self.in_flight.lock().unwrap().get(id).cloned()
+    }
+
+    /// Cancels an in-flight client request. Returns `false` if it already finished.
+    pub(crate) fn cancel_request(&self, id: &Id) -> bool {
+        match self.in_flight.lock().unwrap().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fails every outstanding server-to-client request once the client is gone.
+    pub(crate) fn close(&self) {
+        self.pending.lock().unwrap().clear();
+        for token in self.in_flight.lock().unwrap().values() {
+            token.cancel();
+        }
+    }
+
+    pub(crate) fn subscribe(&self, uri: &str) {
+        self.subscriptions.lock().unwrap().insert(uri.to_string());
+    }
+
+    pub(crate) fn unsubscribe(&self, uri: &str) {
+        self.subscriptions.lock().unwrap().remove(uri);
+    }
+
+    pub fn is_subscribed(&self, uri: &str) -> bool {
+        self.subscriptions.lock().unwrap().contains(uri)
+    }
+}
+
+pub(crate) struct InFlightRequest {
+    session: Arc<Session>,
+    id: Id,
+    token: CancellationToken,
+}
+
+impl InFlightRequest {
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.session.in_flight.lock().unwrap().remove(&self.id);
+    }
+}
+
+struct PendingRequest<'a> {
+    session: &'a Session,
+    id: u64,
+}
+
+impl Drop for PendingRequest<'_> {
+    fn drop(&mut self) {
+        self.session.pending.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[async_trait]
+impl McpPeer for Session {
+    fn session_id(&self) -> &str {
+        self.id()
+    }
+
+    fn protocol_version(&self) -> Option<&str> {
+        Session::protocol_version(self)
+    }
+
+    fn client_info(&self) -> Option<Implementation> {
+        Session::client_info(self)
+    }
+
+    fn client_capabilities(&self) -> Option<ClientCapabilities> {
+        Session::client_capabilities(self)
+    }
+
+    fn log_level(&self) -> LoggingLevel {
+        Session::log_level(self)
+    }
+
+    fn notify(&self, method: &str, params: Value) -> bool {
+        Session::notify(self, method, params)
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, PeerError> {
+        Session::request(self, method, params).await
+    }
+
+    async fn list_roots(&self) -> Result<Vec<Root>, PeerError> {
+        Session::roots(self).await
+    }
+
+    async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<CreateMessageResult, PeerError> {
+        Session::create_message(self, request).await
+    }
+
+    async fn elicit(&self, request: ElicitRequest) -> Result<ElicitResult, PeerError> {
+        Session::elicit(self, request).await
+    }
+}
+
+/// Handle for telling subscribed clients that a resource changed.
+///
+/// Obtained from `McpServer::resource_notifier`; cheap to clone and safe to move into
+/// background tasks that watch files, database rows and so on.
+#[derive(Clone)]
+pub struct ResourceNotifier {
+    sessions: SessionMap,
+}
+
+impl ResourceNotifier {
+    pub(crate) fn new(sessions: SessionMap) -> Self {
+        Self { sessions }
+    }
+
+    /// Emits `notifications/resources/updated` for `uri` to every session subscribed to it,
+    /// returning how many sessions were notified.
+    pub fn notify_updated(&self, uri: &str) -> usize {
+        let sessions = self.sessions.read().unwrap();
+        sessions
+            .values()
+            .filter(|session| session.is_subscribed(uri))
+            .filter(|session| {
+                session.notify(
+                    "notifications/resources/updated",
+                    serde_json::json!({ "uri": uri }),
+                )
+            })
+            .count()
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+/// A parsed RFC 6570 URI template supporting simple (`{var}`) and reserved (`{+var}`)
+/// expressions, used to match incoming `resources/read` URIs against registered templates.
+#[derive(Debug, Clone)]
+pub(crate) struct UriTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Variable { name: String, reserved: bool },
+}
+
+impl UriTemplate {
+    pub(crate) fn parse(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let expr = &rest[start + 1..start + len];
+            let (name, reserved) = match expr.strip_prefix('+') {
+                Some(name) => (name, true),
+                None => (expr, false),
+            };
+            parts.push(Part::Variable {
+                name: name.to_string(),
+                reserved,
+            });
+            rest = &rest[start + len + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        Self { parts }
+    }
+
+    /// Matches `uri` against the template, returning the extracted variables on success.
+    pub(crate) fn matches(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut variables = HashMap::new();
+        if Self::match_parts(&self.parts, uri, &mut variables) {
+            Some(variables)
+        } else {
+            None
+        }
+    }
+
+    fn match_parts(parts: &[Part], input: &str, variables: &mut HashMap<String, String>) -> bool {
+        let Some((part, rest)) = parts.split_first() else {
+            return input.is_empty();
+        };
+
+        match part {
+            Part::Literal(literal) => match input.strip_prefix(literal.as_str()) {
+                Some(remaining) => Self::match_parts(rest, remaining, variables),
+                None => false,
+            },
+            Part::Variable { name, reserved } => {
+                // Try the longest candidate first so trailing variables consume the remainder.
+                let limit = if *reserved {
+                    input.len()
+                } else {
+                    input.find('/').unwrap_or(input.len())
+                };
+                for end in (1..=limit).rev().filter(|&end| input.is_char_boundary(end)) {
+                    if Self::match_parts(rest, &input[end..], variables) {
+                        variables.insert(name.clone(), input[..end].to_string());
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_variable() {
+        let template = UriTemplate::parse("db://users/{id}");
+        let vars = template.matches("db://users/42").unwrap();
+        assert_eq!(vars["id"], "42");
+
+        assert!(template.matches("db://users/42/posts").is_none());
+        assert!(template.matches("db://users/").is_none());
+        assert!(template.matches("db://groups/42").is_none());
+    }
+
+    #[test]
+    fn test_reserved_variable() {
+        let template = UriTemplate::parse("file:///{+path}");
+        let vars = template.matches("file:///etc/app/config.toml").unwrap();
+        assert_eq!(vars["path"], "etc/app/config.toml");
+    }
+
+    #[test]
+    fn test_multiple_variables() {
+        let template = UriTemplate::parse("repo://{owner}/{name}/readme");
+        let vars = template.matches("repo://avahowell/offeryn/readme").unwrap();
+        assert_eq!(vars["owner"], "avahowell");
+        assert_eq!(vars["name"], "offeryn");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::server::Session;
+use crate::McpServer;
+use axum::response::sse::Event;
+use jsonrpc_core::Call;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+pub(crate) type EventSender = mpsc::Sender<Result<Event, Infallible>>;
+
+/// Work handed to a session's forwarder. It is applied only after everything the session
+/// sent before it has been forwarded, so e.g. a tool's progress notifications always reach
+/// the client ahead of its response.
+enum StreamCommand {
+    /// Write a response to a stream
+    Respond(EventSender, Event),
+    /// Stop routing server-initiated messages to a stream
+    Remove(EventSender),
+}
+
+struct SessionStreams {
+    streams: Vec<EventSender>,
+    commands: mpsc::UnboundedSender<StreamCommand>,
+}
+
+/// Session bookkeeping shared by the HTTP transports.
+///
+/// Each transport session owns a stack of open SSE streams. Server-initiated messages for
+/// the session are written to the most recently opened stream that is still alive, so a
+/// streaming POST response receives the notifications emitted while it is in flight and
+/// everything else falls back to the long-lived stream. Server-to-client requests fail with
+/// `PeerError::Disconnected` when no stream is open to carry them.
+#[derive(Default)]
+pub(crate) struct HttpSessions {
+    streams: Mutex<HashMap<String, SessionStreams>>,
+}
+
+impl HttpSessions {
+    /// Registers a new session with `server` and starts forwarding its server-initiated
+    /// messages onto the session's streams.
+    pub(crate) fn open(self: &Arc<Self>, server: &McpServer) -> Arc<Session> {
+        let session_id = Uuid::new_v4().to_string();
+        let (notification_tx, mut notification_rx) = mpsc::unbounded_channel();
+        let session = server.connect(session_id.clone(), notification_tx);
+
+        let (commands, mut command_rx) = mpsc::unbounded_channel();
+
+        let active_sessions = {
+            let mut streams = self.streams.lock().unwrap();
+            streams.insert(
+                session_id.clone(),
+                SessionStreams {
+                    streams: Vec::new(),
+                    commands,
+                },
+            );
+            streams.len()
+        };
+        info!(
+            session_id = %session_id,
+            active_sessions = %active_sessions,
+            "Opened HTTP session"
+        );
+
+        // The session owns the sender this task reads from, so only hold on to it weakly
+        let weak_session = Arc::downgrade(&session);
+        let sessions = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    call = notification_rx.recv() => match call {
+                        Some(call) => sessions.forward(&session_id, &weak_session, call).await,
+                        None => break,
+                    },
+                    Some(command) = command_rx.recv() => {
+                        // Anything the session sent before the command is already queued
+                        while let Ok(call) = notification_rx.try_recv() {
+                            sessions.forward(&session_id, &weak_session, call).await;
+                        }
+                        match command {
+                            StreamCommand::Respond(tx, event) => {
+                                let _ = tx.send(Ok(event)).await;
+                            }
+                            StreamCommand::Remove(tx) => {
+                                if let Some(session) =
+                                    sessions.streams.lock().unwrap().get_mut(&session_id)
+                                {
+                                    session.streams.retain(|stream| !stream.same_channel(&tx));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        session
+    }
+
+    /// Writes a server-initiated message to the session's current stream. A request that
+    /// cannot be delivered is failed right away rather than left waiting for an answer.
+    async fn forward(&self, session_id: &str, session: &Weak<Session>, call: Call) {
+        let data = match serde_json::to_string(&call) {
+            Ok(data) => data,
+            Err(e) => {
+                error!(
+                    session_id = %session_id,
+                    error = %e,
+                    "Failed to serialize notification"
+                );
+                return;
+            }
+        };
+        let sent = match self.stream(session_id) {
+            Some(tx) => tx
+                .send(Ok(Event::default().event("message").data(data)))
+                .await
+                .is_ok(),
+            None => false,
+        };
+        if sent {
+            return;
+        }
+        debug!(
+            session_id = %session_id,
+            "No open stream, dropping server-initiated message"
+        );
+        if let (Call::MethodCall(request), Some(session)) = (&call, session.upgrade()) {
+            session.abandon_request(&request.id);
+        }
+    }
+
+    /// Tears down the session, returning `false` if it was not open.
+    pub(crate) fn close(&self, server: &McpServer, session_id: &str) -> bool {
+        let removed = self.streams.lock().unwrap().remove(session_id).is_some();
+        if removed {
+            server.disconnect(session_id);
+            info!(session_id = %session_id, "Closed HTTP session");
+        }
+        removed
+    }
+
+    pub(crate) fn contains(&self, session_id: &str) -> bool {
+        self.streams.lock().unwrap().contains_key(session_id)
+    }
+
+    pub(crate) fn push_stream(&self, session_id: &str, tx: EventSender) -> bool {
+        match self.streams.lock().unwrap().get_mut(session_id) {
+            Some(session) => {
+                session.streams.push(tx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops routing messages to `tx` once the ones already sent have been forwarded.
+    pub(crate) fn remove_stream(&self, session_id: &str, tx: &EventSender) {
+        self.command(session_id, StreamCommand::Remove(tx.clone()));
+    }
+
+    /// Writes a response to `tx` after the messages the session has already sent.
+    pub(crate) fn respond(&self, session_id: &str, tx: &EventSender, event: Event) {
+        self.command(session_id, StreamCommand::Respond(tx.clone(), event));
+    }
+
+    fn command(&self, session_id: &str, command: StreamCommand) {
+        if let Some(session) = self.streams.lock().unwrap().get(session_id) {
+            let _ = session.commands.send(command);
+        }
+    }
+
+    /// The stream server-initiated messages for `session_id` should currently go to.
+    pub(crate) fn stream(&self, session_id: &str) -> Option<EventSender> {
+        let mut sessions = self.streams.lock().unwrap();
+        let session = sessions.get_mut(session_id)?;
+        session.streams.retain(|stream| !stream.is_closed());
+        session.streams.last().cloned()
+    }
+}
@@ -1,4 +1,43 @@
+mod http_sessions;
 mod sse;
 mod stdio;
+mod streamable_http;
+mod websocket;
 pub use sse::SseServerTransport;
 pub use stdio::StdioServerTransport;
+pub use streamable_http::{StreamableHttpServerTransport, SESSION_ID_HEADER};
+pub use websocket::WebSocketServerTransport;
+
+use jsonrpc_core::{Call, Output, Request as JsonRpcRequest};
+use serde_json::Value;
+
+/// A message received from a client: either a request or notification for the server, or
+/// the response to a request the server sent.
+pub(crate) enum IncomingMessage {
+    Request(JsonRpcRequest),
+    Response(Output),
+}
+
+impl IncomingMessage {
+    pub(crate) fn from_value(value: Value) -> Result<Self, serde_json::Error> {
+        let is_response = value.get("method").is_none()
+            && (value.get("result").is_some() || value.get("error").is_some());
+        if is_response {
+            serde_json::from_value(value).map(IncomingMessage::Response)
+        } else {
+            serde_json::from_value(value).map(IncomingMessage::Request)
+        }
+    }
+}
+
+/// Whether the client expects a response to `request`. Notifications get none, and neither
+/// does a batch made up only of notifications.
+pub(crate) fn expects_response(request: &JsonRpcRequest) -> bool {
+    match request {
+        JsonRpcRequest::Single(call) => !matches!(call, Call::Notification(_)),
+        // An empty batch is answered with an error
+        JsonRpcRequest::Batch(calls) => {
+            calls.is_empty() || calls.iter().any(|c| !matches!(c, Call::Notification(_)))
+        }
+    }
+}
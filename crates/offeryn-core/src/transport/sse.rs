@@ -1,46 +1,56 @@
-use crate::McpServer;
+use super::http_sessions::HttpSessions;
+use super::{expects_response, IncomingMessage};
+use crate::{McpError, McpServer};
 use async_stream::stream;
 use axum::{
     extract::{Json, Query},
     http::StatusCode,
-    response::sse::{Event, Sse},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
     routing::{get, post},
     Extension, Router,
 };
 use futures::stream::Stream;
-use jsonrpc_core::{Output, Request as JsonRpcRequest, Response as JsonRpcResponse};
-use std::convert::Infallible;
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
+use jsonrpc_core::{
+    Call, Failure, Id, Output, Request as JsonRpcRequest, Response as JsonRpcResponse, Version,
 };
+use std::convert::Infallible;
+use std::{collections::HashMap, sync::Arc};
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
-use uuid::Uuid;
 
 pub struct SseServerTransport {
-    connections: HashMap<String, mpsc::Sender<Result<Event, Infallible>>>,
+    connections: Arc<HttpSessions>,
+}
+
+impl Default for SseServerTransport {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SseServerTransport {
     pub fn new() -> Self {
         info!("Creating new SSE transport");
         Self {
-            connections: HashMap::new(),
+            connections: Arc::new(HttpSessions::default()),
         }
     }
 
     pub fn create_router(server: Arc<McpServer>) -> Router {
         info!("Creating SSE router");
-        let state = Arc::new(Mutex::new(Self::new()));
+        let state = Arc::new(Self::new());
 
         Router::new()
             .route(
                 "/sse",
                 get(
-                    |Extension(state): Extension<Arc<Mutex<SseServerTransport>>>| async move {
+                    |Extension(state): Extension<Arc<SseServerTransport>>,
+                     Extension(server): Extension<Arc<McpServer>>| async move {
                         info!("New SSE connection request received");
-                        Self::sse_handler(state).await
+                        Self::sse_handler(state, server).await
                     },
                 ),
             )
@@ -48,9 +58,9 @@ impl SseServerTransport {
                 "/message",
                 post(
                     |Query(params): Query<HashMap<String, String>>,
-                     Extension(state): Extension<Arc<Mutex<SseServerTransport>>>,
+                     Extension(state): Extension<Arc<SseServerTransport>>,
                      Extension(server): Extension<Arc<McpServer>>,
-                     Json(request): Json<JsonRpcRequest>| async move {
+                     Json(message): Json<serde_json::Value>| async move {
                         let session_id = match params.get("sessionId") {
                             Some(id) => id,
                             None => {
@@ -64,7 +74,7 @@ impl SseServerTransport {
                             "Received JSON-RPC request"
                         );
 
-                        Self::message_handler(session_id.clone(), state, server, request).await
+                        Self::message_handler(session_id.clone(), state, server, message).await
                     },
                 ),
             )
@@ -81,27 +91,27 @@ impl SseServerTransport {
     }
 
     async fn sse_handler(
-        state: Arc<Mutex<SseServerTransport>>,
+        state: Arc<SseServerTransport>,
+        server: Arc<McpServer>,
     ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
         let (tx, mut rx) = mpsc::channel(100);
-        let session_id = Uuid::new_v4().to_string();
+        let session = state.connections.open(&server);
+        let session_id = session.id().to_string();
+        state.connections.push_stream(&session_id, tx);
 
         info!(
             session_id = %session_id,
             "New SSE connection established"
         );
 
-        {
-            let mut state = state.lock().unwrap();
-            state.connections.insert(session_id.clone(), tx);
-            info!(
-                session_id = %session_id,
-                active_connections = %state.connections.len(),
-                "Added new SSE connection"
-            );
-        }
+        let guard = ConnectionGuard {
+            session_id: session_id.clone(),
+            state,
+            server,
+        };
 
         let stream = stream! {
+            let _guard = guard;
             info!(
                 session_id = %session_id,
                 "Sending endpoint URL"
@@ -138,83 +148,136 @@ impl SseServerTransport {
 
     async fn message_handler(
         session_id: String,
-        state: Arc<Mutex<SseServerTransport>>,
+        state: Arc<SseServerTransport>,
         server: Arc<McpServer>,
-        request: JsonRpcRequest,
-    ) -> Result<Json<JsonRpcResponse>, StatusCode> {
+        message: serde_json::Value,
+    ) -> Result<Response, StatusCode> {
         // Get the sender from the state
-        let tx = {
-            let state = state.lock().unwrap();
-            if !state.connections.contains_key(&session_id) {
-                warn!(
-                    session_id = %session_id,
-                    "Session ID not found"
-                );
-                return Err(StatusCode::NOT_FOUND);
-            }
-            info!(
+        let tx = state.connections.stream(&session_id).ok_or_else(|| {
+            warn!(
                 session_id = %session_id,
-                "Found existing connection"
+                "Session ID not found"
             );
-            state.connections.get(&session_id).cloned().ok_or_else(|| {
-                error!(
-                    session_id = %session_id,
-                    "Failed to get connection sender"
-                );
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?
-        };
+            StatusCode::NOT_FOUND
+        })?;
+        info!(
+            session_id = %session_id,
+            "Found existing connection"
+        );
 
-        // Process request with server
-        let response = server.handle_request(request).await.map_err(|e| {
-            error!(
+        let session = server.session(&session_id).ok_or_else(|| {
+            warn!(
                 session_id = %session_id,
-                error = %e,
-                "Server request handler failed"
+                "Session not registered with server"
             );
-            StatusCode::INTERNAL_SERVER_ERROR
+            StatusCode::NOT_FOUND
         })?;
 
-        // Send response through SSE channel if it's a successful response
-        if let JsonRpcResponse::Single(Output::Success(_)) = &response {
-            // Ensure we send a proper JSON-RPC message
-            let event =
-                Event::default()
-                    .event("message")
-                    .data(serde_json::to_string(&response).map_err(|e| {
-                        error!(
-                            session_id = %session_id,
-                            error = %e,
-                            "Failed to serialize response"
-                        );
-                        StatusCode::INTERNAL_SERVER_ERROR
-                    })?);
+        let request = match IncomingMessage::from_value(message) {
+            Ok(IncomingMessage::Request(request)) => request,
+            Ok(IncomingMessage::Response(response)) => {
+                server.handle_session_response(&session, response);
+                return Ok(StatusCode::ACCEPTED.into_response());
+            }
+            Err(e) => {
+                warn!(
+                    session_id = %session_id,
+                    error = %e,
+                    "Failed to parse JSON-RPC message"
+                );
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        };
 
+        // Notifications get no reply over the event stream
+        if !expects_response(&request) {
             info!(
                 session_id = %session_id,
-                "Sending JSON-RPC response through SSE"
+                "Skipping SSE for notification"
             );
+            server
+                .handle_session_request(&session, request)
+                .await
+                .map_err(|e| {
+                    error!(
+                        session_id = %session_id,
+                        error = %e,
+                        "Server request handler failed"
+                    );
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            return Ok(StatusCode::ACCEPTED.into_response());
+        }
+        let request_id = match &request {
+            JsonRpcRequest::Single(Call::MethodCall(call)) => call.id.clone(),
+            _ => Id::Null,
+        };
 
-            tx.send(Ok(event)).await.map_err(|e| {
-                error!(
-                    session_id = %session_id,
-                    error = %e,
-                    "Failed to send response through SSE channel"
-                );
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-        } else {
+        // The response travels over the event stream, so acknowledge the POST right away
+        // instead of holding it open while the request runs. Clients post their messages one
+        // at a time, and waiting here would serialize their requests.
+        tokio::spawn(async move {
+            let response = match server.handle_session_request(&session, request).await {
+                Ok(Some(response)) => response,
+                Ok(None) => return,
+                Err(McpError::RequestCancelled) => {
+                    info!(
+                        session_id = %session_id,
+                        "Request cancelled, suppressing response"
+                    );
+                    return;
+                }
+                Err(e) => {
+                    error!(
+                        session_id = %session_id,
+                        error = %e,
+                        "Server request handler failed"
+                    );
+                    JsonRpcResponse::Single(Output::Failure(Failure {
+                        jsonrpc: Some(Version::V2),
+                        error: e.into(),
+                        id: request_id,
+                    }))
+                }
+            };
+
+            let data = match serde_json::to_string(&response) {
+                Ok(data) => data,
+                Err(e) => {
+                    error!(
+                        session_id = %session_id,
+                        error = %e,
+                        "Failed to serialize response"
+                    );
+                    return;
+                }
+            };
             info!(
                 session_id = %session_id,
-                "Skipping SSE for notification or error response"
+                "Sending JSON-RPC response through SSE"
             );
-        }
+            state.connections.respond(
+                &session_id,
+                &tx,
+                Event::default().event("message").data(data),
+            );
+        });
 
-        info!(
-            session_id = %session_id,
-            "Request completed successfully"
-        );
-        Ok(Json(response))
+        Ok(StatusCode::ACCEPTED.into_response())
+    }
+}
+
+/// Tears down both the transport connection and the server session once the SSE stream
+/// is dropped, i.e. when the client disconnects.
+struct ConnectionGuard {
+    session_id: String,
+    state: Arc<SseServerTransport>,
+    server: Arc<McpServer>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.state.connections.close(&self.server, &self.session_id);
     }
 }
 
@@ -284,7 +347,7 @@ mod tests {
             id: Id::Num(1),
         }));
 
-        let response = server.handle_request(request).await.unwrap();
+        let response = server.handle_request(request).await.unwrap().unwrap();
         if let JsonRpcResponse::Single(Output::Success(success)) = response {
             let result: Value = success.result;
             let content = result.get("content").unwrap().as_array().unwrap();
@@ -313,7 +376,7 @@ mod tests {
             id: Id::Num(2),
         }));
 
-        let response = server.handle_request(request).await.unwrap();
+        let response = server.handle_request(request).await.unwrap().unwrap();
         if let JsonRpcResponse::Single(Output::Success(success)) = response {
             let result: Value = success.result;
             let content = result.get("content").unwrap().as_array().unwrap();
@@ -342,7 +405,7 @@ mod tests {
             id: Id::Num(3),
         }));
 
-        let response = server.handle_request(request).await.unwrap();
+        let response = server.handle_request(request).await.unwrap().unwrap();
         if let JsonRpcResponse::Single(Output::Success(success)) = response {
             let result: Value = success.result;
             let content = result.get("content").unwrap().as_array().unwrap();
@@ -364,4 +427,116 @@ mod tests {
         // Create the router
         let _app = SseServerTransport::create_router(server);
     }
+
+    #[tokio::test]
+    async fn test_batch_request() {
+        use crate::client::{ClientTransport, SseClientTransport};
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Calculator::default()).await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, SseServerTransport::create_router(server))
+                .await
+                .unwrap();
+        });
+
+        let (outgoing, mut incoming) = SseClientTransport::new(&format!("http://{}/sse", addr))
+            .start()
+            .await
+            .unwrap();
+        // Batching was dropped in 2025-06-18
+        outgoing
+            .send(json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "initialize",
+                "params": { "protocolVersion": offeryn_types::PROTOCOL_VERSION_2025_03_26 }
+            }))
+            .await
+            .unwrap();
+        assert_eq!(incoming.recv().await.unwrap()["id"], 0);
+
+        let multiply = |id: u64, a: i64| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "calculator_multiply", "arguments": { "a": a, "b": 3 } }
+            })
+        };
+        outgoing
+            .send(json!([
+                multiply(1, 2),
+                { "jsonrpc": "2.0", "method": "notifications/initialized" },
+                { "jsonrpc": "2.0", "id": 2, "method": "no/such/method" },
+                multiply(3, 5),
+            ]))
+            .await
+            .unwrap();
+
+        let response = incoming.recv().await.unwrap();
+        let mut outputs = response.as_array().unwrap().clone();
+        outputs.sort_by_key(|output| output["id"].as_u64());
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[0]["result"]["content"][0]["text"], "6");
+        assert_eq!(outputs[1]["error"]["code"], -32601);
+        assert_eq!(outputs[2]["result"]["content"][0]["text"], "15");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_gets_no_response() {
+        #[derive(Default)]
+        struct Sleeper {}
+
+        #[tool]
+        impl Sleeper {
+            async fn forever(&self) -> String {
+                std::future::pending().await
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Sleeper::default()).await;
+        let state = Arc::new(SseServerTransport::new());
+        let (tx, mut rx) = mpsc::channel(100);
+        let session = state.connections.open(&server);
+        let session_id = session.id().to_string();
+        state.connections.push_stream(&session_id, tx);
+
+        let post = |message: Value| {
+            SseServerTransport::message_handler(
+                session_id.clone(),
+                state.clone(),
+                server.clone(),
+                message,
+            )
+        };
+        post(json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }))
+            .await
+            .unwrap();
+        assert!(rx.recv().await.is_some());
+
+        let response = post(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "sleeper_forever", "arguments": {} }
+        }))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        post(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": { "requestId": 1 }
+        }))
+        .await
+        .unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+    }
 }
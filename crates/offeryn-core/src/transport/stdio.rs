@@ -1,13 +1,19 @@
-use crate::McpServer;
+use super::IncomingMessage;
+use crate::server::{InFlightRequest, Session};
+use crate::{McpError, McpServer};
 use axum::async_trait;
 use jsonrpc_core::{Call, Error, Failure, Id, Output, Request, Response, Version};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::{
     io::{
         stdin, stdout, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter,
     },
-    sync::mpsc,
+    sync::{mpsc, Semaphore},
+    task::JoinSet,
 };
+use tracing::{info, warn};
+use uuid::Uuid;
 
 #[async_trait]
 trait StdioTransport<R, W>
@@ -38,6 +44,14 @@ where
     }
 }
 
+/// Serves a single MCP session over newline-delimited JSON on a pair of streams, by
+/// default the process's stdin and stdout.
+///
+/// Requests are handled concurrently, up to a configurable limit beyond which they queue,
+/// and their responses written as they complete. Input is read the whole time, so
+/// notifications, cancellations and the client's answers to server requests are handled
+/// as they arrive even while every slot is taken. At end of input the transport waits for
+/// queued and in-flight requests to finish before shutting down.
 pub struct StdioServerTransport<R, W>
 where
     R: AsyncRead + Unpin + Send + 'static,
@@ -46,15 +60,13 @@ where
     server: Arc<McpServer>,
     stdin: R,
     stdout: W,
+    max_concurrent_requests: usize,
+    drain_timeout: Duration,
 }
 
 impl StdioServerTransport<tokio::io::Stdin, tokio::io::Stdout> {
     pub fn new(server: Arc<McpServer>) -> Self {
-        Self {
-            server,
-            stdin: stdin(),
-            stdout: stdout(),
-        }
+        Self::with_streams(server, stdin(), stdout())
     }
 }
 
@@ -68,24 +80,71 @@ where
             server,
             stdin,
             stdout,
+            max_concurrent_requests: 32,
+            drain_timeout: Duration::from_secs(30),
         }
     }
 
+    /// Sets how many requests may be handled at once. Requests beyond the limit wait in
+    /// arrival order for a slot; cancelling one drops it from the queue.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    /// Sets how long to wait for in-flight requests at end of input before cancelling them.
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
-        let (tx, mut rx) = mpsc::channel(100);
+        let (tx, mut rx) = mpsc::channel::<serde_json::Value>(100);
         let mut reader = BufReader::new(self.stdin);
 
+        // Server-initiated messages are written by the same task as responses, so a tool's
+        // progress and log notifications can be flushed ahead of its response
+        let (notification_tx, mut notification_rx) = mpsc::unbounded_channel::<Call>();
+        let session = self
+            .server
+            .connect(Uuid::new_v4().to_string(), notification_tx);
+
         let response_handler = tokio::spawn({
             let mut writer = BufWriter::new(self.stdout);
             async move {
-                while let Some(response) = rx.recv().await {
-                    let response_json = serde_json::to_vec(&response)?;
-                    Self::write_message(&mut writer, &response_json).await?;
+                loop {
+                    let response = tokio::select! {
+                        biased;
+                        Some(call) = notification_rx.recv() => {
+                            Self::write_value(&mut writer, &call).await?;
+                            continue;
+                        }
+                        response = rx.recv() => response,
+                    };
+                    // Whatever a request sent before finishing goes out ahead of its response
+                    while let Ok(call) = notification_rx.try_recv() {
+                        Self::write_value(&mut writer, &call).await?;
+                    }
+                    match response {
+                        Some(response) => Self::write_value(&mut writer, &response).await?,
+                        None => break,
+                    }
                 }
                 Ok::<_, std::io::Error>(())
             }
         });
 
+        // Calls wait for a slot in the dispatcher rather than here, so the loop keeps
+        // reading responses and cancellations while every slot is taken
+        let (queue_tx, queue_rx) = mpsc::unbounded_channel();
+        let mut dispatcher = tokio::spawn(Self::dispatch(
+            self.server.clone(),
+            session.clone(),
+            tx.clone(),
+            queue_rx,
+            self.max_concurrent_requests,
+        ));
+
         loop {
             let message = match Self::read_message(&mut reader).await {
                 Ok(msg) => msg,
@@ -93,53 +152,177 @@ where
                 Err(_) => continue,
             };
 
-            let request: Request = match serde_json::from_slice(&message) {
-                Ok(req) => req,
-                Err(_) => {
-                    let id = serde_json::from_slice::<serde_json::Value>(&message)
-                        .ok()
-                        .and_then(|v| v.get("id").cloned())
-                        .and_then(|id| id.as_u64())
-                        .map_or(Id::Num(0), Id::Num);
-
-                    let error_response = Response::Single(Output::Failure(Failure {
-                        jsonrpc: Some(Version::V2),
-                        error: Error::parse_error(),
-                        id,
-                    }));
-                    let _ = tx.send(error_response).await;
-                    continue;
-                }
-            };
+            let request =
+                match serde_json::from_slice(&message).and_then(IncomingMessage::from_value) {
+                    Ok(IncomingMessage::Request(request)) => request,
+                    Ok(IncomingMessage::Response(response)) => {
+                        self.server.handle_session_response(&session, response);
+                        continue;
+                    }
+                    Err(_) => {
+                        let id = serde_json::from_slice::<serde_json::Value>(&message)
+                            .ok()
+                            .and_then(|v| v.get("id").cloned())
+                            .and_then(|id| serde_json::from_value(id).ok())
+                            .unwrap_or(Id::Null);
 
-            match self.server.handle_request(request.clone()).await {
-                Ok(response) => {
-                    if tx.send(response).await.is_err() {
-                        break;
+                        let error_response = Response::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: Error::parse_error(),
+                            id,
+                        }));
+                        let _ = tx.send(serde_json::to_value(error_response)?).await;
+                        continue;
                     }
+                };
+
+            // Notifications are cheap and order-sensitive, so handle them inline
+            if let Request::Single(Call::Notification(_)) = &request {
+                if let Some(response) = Self::respond(&self.server, &session, request, None).await {
+                    let _ = tx.send(response).await;
                 }
-                Err(_) => {
-                    let id = match &request {
-                        Request::Single(Call::MethodCall(m)) => m.id.clone(),
-                        Request::Single(Call::Notification(_)) => Id::Num(0),
-                        _ => Id::Num(0),
-                    };
-                    let error_response = Response::Single(Output::Failure(Failure {
-                        jsonrpc: Some(Version::V2),
-                        error: Error::internal_error(),
-                        id,
-                    }));
-                    if tx.send(error_response).await.is_err() {
-                        break;
+                continue;
+            }
+
+            // Track the call before it is queued, so it can be cancelled meanwhile
+            let tracked = match &request {
+                Request::Single(call) => match self.server.track_call(&session, call) {
+                    Ok(tracked) => tracked,
+                    Err(e) => {
+                        let id = match call {
+                            Call::MethodCall(call) => call.id.clone(),
+                            _ => Id::Null,
+                        };
+                        let error_response = Response::Single(Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: e.into(),
+                            id,
+                        }));
+                        let _ = tx.send(serde_json::to_value(error_response)?).await;
+                        continue;
                     }
-                }
+                },
+                Request::Batch(_) => None,
+            };
+
+            if queue_tx.send((request, tracked)).is_err() {
+                break;
             }
         }
 
+        drop(queue_tx);
+        let drained = tokio::time::timeout(self.drain_timeout, &mut dispatcher).await;
+        if drained.is_err() {
+            warn!(
+                session_id = %session.id(),
+                "Timed out draining requests, cancelling the rest"
+            );
+        }
+
+        // Disconnecting cancels whatever is still running
+        self.server.disconnect(session.id());
+        if drained.is_err() {
+            let _ = dispatcher.await;
+        }
+        drop(session);
         drop(tx);
         let _ = response_handler.await?;
         Ok(())
     }
+
+    /// Serializes `value` and writes it as one line.
+    async fn write_value(
+        writer: &mut BufWriter<W>,
+        value: &impl serde::Serialize,
+    ) -> Result<(), std::io::Error> {
+        let json = serde_json::to_vec(value)?;
+        Self::write_message(writer, &json).await
+    }
+
+    /// Starts queued calls in arrival order as slots free up, then waits for the running
+    /// ones once the queue closes at end of input.
+    async fn dispatch(
+        server: Arc<McpServer>,
+        session: Arc<Session>,
+        tx: mpsc::Sender<serde_json::Value>,
+        mut queue: mpsc::UnboundedReceiver<(Request, Option<InFlightRequest>)>,
+        max_concurrent_requests: usize,
+    ) {
+        let permits = Arc::new(Semaphore::new(max_concurrent_requests));
+        let mut in_flight = JoinSet::new();
+        while let Some((request, tracked)) = queue.recv().await {
+            let acquire = permits.clone().acquire_owned();
+            // A call cancelled while it waits never starts
+            let permit = match &tracked {
+                Some(tracked) => tokio::select! {
+                    biased;
+                    _ = tracked.token().cancelled() => continue,
+                    permit = acquire => permit,
+                },
+                None => acquire.await,
+            };
+            let Ok(permit) = permit else {
+                break;
+            };
+
+            // Every call gets its own task so a slow tool, or one waiting on the client for
+            // e.g. `roots/list`, doesn't hold up the rest of the connection
+            in_flight.spawn({
+                let server = server.clone();
+                let session = session.clone();
+                let tx = tx.clone();
+                async move {
+                    let _permit = permit;
+                    if let Some(response) = Self::respond(&server, &session, request, tracked).await
+                    {
+                        let _ = tx.send(response).await;
+                    }
+                }
+            });
+            // Reap finished requests so the set only holds live ones
+            while in_flight.try_join_next().is_some() {}
+        }
+
+        if !in_flight.is_empty() {
+            info!(
+                session_id = %session.id(),
+                in_flight = %in_flight.len(),
+                "End of input, waiting for in-flight requests"
+            );
+        }
+        while in_flight.join_next().await.is_some() {}
+    }
+
+    /// Handles one request, returning the response to write, if any. `tracked` is set for
+    /// calls the read loop already started tracking.
+    async fn respond(
+        server: &McpServer,
+        session: &Arc<Session>,
+        request: Request,
+        tracked: Option<InFlightRequest>,
+    ) -> Option<serde_json::Value> {
+        let id = match &request {
+            Request::Single(Call::MethodCall(m)) => m.id.clone(),
+            _ => Id::Null,
+        };
+        let handled = match (request, tracked) {
+            (Request::Single(call), Some(tracked)) => {
+                server.handle_tracked_call(session, call, tracked).await
+            }
+            (request, _) => server.handle_session_request(session, request).await,
+        };
+        let response = match handled {
+            Ok(Some(response)) => response,
+            // Notifications and cancelled requests get no response
+            Ok(None) | Err(McpError::RequestCancelled) => return None,
+            Err(e) => Response::Single(Output::Failure(Failure {
+                jsonrpc: Some(Version::V2),
+                error: e.into(),
+                id,
+            })),
+        };
+        serde_json::to_value(response).ok()
+    }
 }
 
 #[async_trait]
@@ -167,6 +350,42 @@ mod tests {
         }
     }
 
+    async fn initialize(
+        writer: &mut BufWriter<DuplexStream>,
+        reader: &mut BufReader<DuplexStream>,
+    ) {
+        initialize_with_version(writer, reader, offeryn_types::LATEST_PROTOCOL_VERSION).await
+    }
+
+    async fn initialize_with_version(
+        writer: &mut BufWriter<DuplexStream>,
+        reader: &mut BufReader<DuplexStream>,
+        protocol_version: &str,
+    ) {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": protocol_version,
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            writer,
+            &serde_json::to_vec(&request).unwrap(),
+        )
+        .await
+        .unwrap();
+        let response_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(reader)
+                .await
+                .unwrap();
+        let response: Response = serde_json::from_slice(&response_bytes).unwrap();
+        assert!(matches!(response, Response::Single(Output::Success(_))));
+    }
+
     #[tokio::test]
     async fn test_calculator_add() {
         let server = Arc::new(McpServer::new("test-server", "1.0.0"));
@@ -195,6 +414,9 @@ mod tests {
         });
 
         let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
         let request_json = serde_json::to_vec(&request).unwrap();
         StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
             &mut client_writer,
@@ -203,7 +425,6 @@ mod tests {
         .await
         .unwrap();
 
-        let mut client_reader = BufReader::new(client_reader);
         let response_bytes =
             StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
                 .await
@@ -222,4 +443,699 @@ mod tests {
             _ => panic!("Expected successful response"),
         }
     }
+
+    #[tokio::test]
+    async fn test_notification_gets_no_response() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+
+        let (client_reader, server_writer) = duplex(1024);
+        let (server_reader, client_writer) = duplex(1024);
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
+        for message in [
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }),
+        ] {
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &serde_json::to_vec(&message).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+
+        // The first thing written back answers the ping
+        let response_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let response: Response = serde_json::from_slice(&response_bytes).unwrap();
+        server_task.abort();
+        assert!(matches!(
+            response,
+            Response::Single(Output::Success(success)) if success.id == Id::Num(1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_batch_request() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Calculator::default()).await;
+
+        let (client_reader, server_writer) = duplex(1024);
+        let (server_reader, client_writer) = duplex(1024);
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        // Batching was dropped in 2025-06-18
+        initialize_with_version(
+            &mut client_writer,
+            &mut client_reader,
+            offeryn_types::PROTOCOL_VERSION_2025_03_26,
+        )
+        .await;
+
+        let add = |id: u64, a: i64| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "calculator_add", "arguments": { "a": a, "b": 1 } }
+            })
+        };
+        let batch = json!([
+            add(1, 1),
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            add(2, 10),
+        ]);
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            &mut client_writer,
+            &serde_json::to_vec(&batch).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let response_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let response: Response = serde_json::from_slice(&response_bytes).unwrap();
+        server_task.abort();
+
+        let Response::Batch(outputs) = response else {
+            panic!("Expected batch response");
+        };
+        let mut sums: Vec<(Id, String)> = outputs
+            .into_iter()
+            .map(|output| match output {
+                Output::Success(success) => (
+                    success.id,
+                    success.result["content"][0]["text"]
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                ),
+                Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+            })
+            .collect();
+        sums.sort_by_key(|(id, _)| format!("{:?}", id));
+        assert_eq!(
+            sums,
+            vec![
+                (Id::Num(1), "2".to_string()),
+                (Id::Num(2), "11".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resource_updated_notification() {
+        use async_trait::async_trait;
+        use offeryn_types::{McpResource, ResourceContents};
+
+        struct Readme;
+
+        #[async_trait]
+        impl McpResource for Readme {
+            fn uri(&self) -> &str {
+                "file:///README.md"
+            }
+
+            fn name(&self) -> &str {
+                "README"
+            }
+
+            async fn read(&self) -> Result<Vec<ResourceContents>, String> {
+                Ok(vec![ResourceContents::text(self.uri(), None, "# offeryn")])
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_resource(Readme).await;
+        let notifier = server.resource_notifier();
+
+        let (client_reader, server_writer) = duplex(1024);
+        let (server_reader, client_writer) = duplex(1024);
+
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "resources/subscribe",
+            "params": { "uri": "file:///README.md" }
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            &mut client_writer,
+            &serde_json::to_vec(&request).unwrap(),
+        )
+        .await
+        .unwrap();
+        let response_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let response: Response = serde_json::from_slice(&response_bytes).unwrap();
+        assert!(matches!(response, Response::Single(Output::Success(_))));
+
+        assert_eq!(notifier.notify_updated("file:///README.md"), 1);
+
+        let notification_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let notification: serde_json::Value = serde_json::from_slice(&notification_bytes).unwrap();
+
+        server_task.abort();
+
+        assert_eq!(
+            notification,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": "file:///README.md" }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_to_client_request() {
+        use offeryn_types::RequestContext;
+
+        #[derive(Default)]
+        struct Workspace {}
+
+        #[tool]
+        impl Workspace {
+            async fn roots(&self, ctx: RequestContext) -> Result<serde_json::Value, String> {
+                ctx.send_request("roots/list", json!({}))
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Workspace::default()).await;
+
+        let (client_reader, server_writer) = duplex(1024);
+        let (server_reader, client_writer) = duplex(1024);
+
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "workspace_roots", "arguments": {} }
+        });
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            &mut client_writer,
+            &serde_json::to_vec(&request).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        // The tool asks the client for its roots while the call is in flight
+        let request_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let request: serde_json::Value = serde_json::from_slice(&request_bytes).unwrap();
+        assert_eq!(request["method"], "roots/list");
+
+        let roots = json!({ "roots": [{ "uri": "file:///src", "name": "src" }] });
+        let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": roots });
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            &mut client_writer,
+            &serde_json::to_vec(&response).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let response_bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let response: serde_json::Value = serde_json::from_slice(&response_bytes).unwrap();
+
+        server_task.abort();
+
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["structuredContent"], roots);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_gets_no_response() {
+        #[derive(Default)]
+        struct Sleeper {}
+
+        #[tool]
+        impl Sleeper {
+            async fn forever(&self) -> String {
+                std::future::pending().await
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Sleeper::default()).await;
+
+        let (client_reader, server_writer) = duplex(1024);
+        let (server_reader, client_writer) = duplex(1024);
+
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "sleeper_forever", "arguments": {} }
+        });
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            &mut client_writer,
+            &serde_json::to_vec(&call).unwrap(),
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        for message in [
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": { "requestId": 1 }
+            }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+        ] {
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &serde_json::to_vec(&message).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+
+        // The ping is answered; the cancelled call never is
+        loop {
+            let bytes = StdioServerTransport::<DuplexStream, DuplexStream>::read_message(
+                &mut client_reader,
+            )
+            .await
+            .unwrap();
+            let message: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_ne!(message["id"], 1);
+            if message["id"] == 2 {
+                break;
+            }
+        }
+        drop(client_writer);
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(1), server_task).await;
+        let mut rest = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut client_reader, &mut rest)
+            .await
+            .unwrap();
+        assert!(!String::from_utf8(rest).unwrap().contains(r#""id":1"#));
+    }
+
+    #[derive(Default)]
+    struct Jobs {}
+
+    #[tool]
+    impl Jobs {
+        /// Takes a while
+        async fn slow(&self) -> String {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            "slow".to_string()
+        }
+    }
+
+    /// Calls the slow tool, then pings, and returns the ids in the order their responses arrived.
+    /// With `close_input` set, input ends right after the requests are written.
+    async fn response_order(
+        transport: impl FnOnce(
+            Arc<McpServer>,
+            DuplexStream,
+            DuplexStream,
+        ) -> StdioServerTransport<DuplexStream, DuplexStream>,
+        close_input: bool,
+    ) -> Vec<i64> {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Jobs::default()).await;
+
+        let (client_reader, server_writer) = duplex(4096);
+        let (server_reader, client_writer) = duplex(4096);
+        let transport = transport(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
+        for request in [
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": "jobs_slow", "arguments": {} }
+            }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+        ] {
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &serde_json::to_vec(&request).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+        if close_input {
+            drop(client_writer);
+        }
+
+        let mut order = Vec::new();
+        for _ in 0..2 {
+            let bytes = StdioServerTransport::<DuplexStream, DuplexStream>::read_message(
+                &mut client_reader,
+            )
+            .await
+            .unwrap();
+            let response: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            order.push(response["id"].as_i64().unwrap());
+        }
+        if close_input {
+            server_task.await.unwrap();
+        } else {
+            server_task.abort();
+        }
+        order
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests() {
+        // A slow tool doesn't hold up the requests behind it
+        let order = response_order(StdioServerTransport::with_streams, false).await;
+        assert_eq!(order, vec![2, 1]);
+
+        // Unless only one request may run at a time
+        let order = response_order(
+            |server, reader, writer| {
+                StdioServerTransport::with_streams(server, reader, writer)
+                    .with_max_concurrent_requests(1)
+            },
+            false,
+        )
+        .await;
+        assert_eq!(order, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_drains_requests_at_end_of_input() {
+        let order = response_order(StdioServerTransport::with_streams, true).await;
+        assert_eq!(order, vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_right_behind_request() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Jobs::default()).await;
+
+        let (client_reader, server_writer) = duplex(4096);
+        let (server_reader, client_writer) = duplex(4096);
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
+        // The cancellation is read before the call has had a chance to start
+        for message in [
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": "jobs_slow", "arguments": {} }
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": { "requestId": 1 }
+            }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+        ] {
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &serde_json::to_vec(&message).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+        drop(client_writer);
+        server_task.await.unwrap();
+
+        let mut rest = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut client_reader, &mut rest)
+            .await
+            .unwrap();
+        let rest = String::from_utf8(rest).unwrap();
+        assert!(rest.contains(r#""id":2"#));
+        assert!(!rest.contains(r#""id":1"#));
+    }
+
+    #[tokio::test]
+    async fn test_full_slots_still_read_responses() {
+        use offeryn_types::RequestContext;
+
+        #[derive(Default)]
+        struct Workspace {}
+
+        #[tool]
+        impl Workspace {
+            async fn count_roots(&self, ctx: RequestContext) -> Result<usize, String> {
+                ctx.roots()
+                    .await
+                    .map(|roots| roots.len())
+                    .map_err(|e| e.to_string())
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Workspace::default()).await;
+
+        let (client_reader, server_writer) = duplex(4096);
+        let (server_reader, client_writer) = duplex(4096);
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer)
+            .with_max_concurrent_requests(2);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        let send = |message: serde_json::Value| serde_json::to_vec(&message).unwrap();
+        for message in [
+            json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": offeryn_types::LATEST_PROTOCOL_VERSION,
+                    "capabilities": { "roots": {} },
+                    "clientInfo": { "name": "test-client", "version": "1.0.0" }
+                }
+            }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        ] {
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &send(message),
+            )
+            .await
+            .unwrap();
+        }
+        StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+            .await
+            .unwrap();
+
+        // One more call than there are slots, each of them waiting on the client
+        for id in 1..=3 {
+            let call = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "workspace_count_roots", "arguments": {} }
+            });
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &send(call),
+            )
+            .await
+            .unwrap();
+        }
+
+        let roots = json!({ "roots": [{ "uri": "file:///src", "name": "src" }] });
+        let mut finished = Vec::new();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while finished.len() < 3 {
+                let bytes = StdioServerTransport::<DuplexStream, DuplexStream>::read_message(
+                    &mut client_reader,
+                )
+                .await
+                .unwrap();
+                let message: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+                if message["method"] == "roots/list" {
+                    let response =
+                        json!({ "jsonrpc": "2.0", "id": message["id"], "result": roots });
+                    StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                        &mut client_writer,
+                        &send(response),
+                    )
+                    .await
+                    .unwrap();
+                } else {
+                    assert_eq!(message["result"]["content"][0]["text"], "1");
+                    finished.push(message["id"].as_i64().unwrap());
+                }
+            }
+        })
+        .await
+        .expect("calls waiting on the client deadlocked");
+        finished.sort();
+        assert_eq!(finished, vec![1, 2, 3]);
+        server_task.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_progress_precedes_response() {
+        use offeryn_types::{LoggingLevel, RequestContext};
+
+        #[derive(Default)]
+        struct Indexer {}
+
+        #[tool]
+        impl Indexer {
+            /// Reports completion right before returning
+            async fn index(&self, ctx: RequestContext) -> usize {
+                ctx.log(LoggingLevel::Info, None, json!("indexing"));
+                ctx.report_progress(1.0, Some(1.0), Some("indexed"));
+                1
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Indexer::default()).await;
+
+        let (client_reader, server_writer) = duplex(65536);
+        let (server_reader, client_writer) = duplex(65536);
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        initialize(&mut client_writer, &mut client_reader).await;
+
+        for id in 1..=20 {
+            let call = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "indexer_index", "arguments": {}, "_meta": { "progressToken": id } }
+            });
+            StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+                &mut client_writer,
+                &serde_json::to_vec(&call).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+
+        // Each call's progress is written before its response
+        let mut progressed = std::collections::HashSet::new();
+        let mut responses = 0;
+        while responses < 20 {
+            let bytes = StdioServerTransport::<DuplexStream, DuplexStream>::read_message(
+                &mut client_reader,
+            )
+            .await
+            .unwrap();
+            let message: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            match message["method"].as_str() {
+                Some("notifications/progress") => {
+                    progressed.insert(message["params"]["progressToken"].as_i64().unwrap());
+                }
+                Some(_) => {}
+                None => {
+                    assert!(progressed.contains(&message["id"].as_i64().unwrap()));
+                    responses += 1;
+                }
+            }
+        }
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_parse_error_has_null_id() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        let (client_reader, server_writer) = duplex(4096);
+        let (server_reader, client_writer) = duplex(4096);
+        let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+        let server_task = tokio::spawn(async move {
+            transport.run().await.unwrap();
+        });
+
+        let mut client_writer = BufWriter::new(client_writer);
+        let mut client_reader = BufReader::new(client_reader);
+        StdioServerTransport::<DuplexStream, DuplexStream>::write_message(
+            &mut client_writer,
+            b"{not json",
+        )
+        .await
+        .unwrap();
+        let bytes =
+            StdioServerTransport::<DuplexStream, DuplexStream>::read_message(&mut client_reader)
+                .await
+                .unwrap();
+        let response: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(response["id"], serde_json::Value::Null);
+        assert_eq!(response["error"]["code"], -32700);
+
+        drop(client_writer);
+        server_task.await.unwrap();
+    }
 }
@@ -0,0 +1,608 @@
+use super::http_sessions::HttpSessions;
+use super::{expects_response, IncomingMessage};
+use crate::server::Session;
+use crate::{McpError, McpServer};
+use async_stream::stream;
+use axum::{
+    body::Bytes,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Extension, Json, Router,
+};
+use jsonrpc_core::{
+    Call, Error, Failure, Id, Output, Request as JsonRpcRequest, Response as JsonRpcResponse,
+    Version,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+pub const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// Serves MCP over the Streamable HTTP transport at a single `/mcp` endpoint.
+///
+/// * `POST` carries client messages. Requests are answered either with a JSON body or,
+///   when the client accepts `text/event-stream`, with an SSE stream that also carries any
+///   notifications emitted while the request is handled. Notification-only posts get
+///   `202 Accepted`.
+/// * `GET` opens a long-lived SSE stream for server-initiated messages.
+/// * `DELETE` terminates the session.
+///
+/// Sessions are created by the `initialize` request and identified by the
+/// `Mcp-Session-Id` header on every subsequent request.
+#[derive(Clone)]
+pub struct StreamableHttpServerTransport {
+    sessions: Arc<HttpSessions>,
+    json_response: bool,
+}
+
+impl Default for StreamableHttpServerTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamableHttpServerTransport {
+    pub fn new() -> Self {
+        info!("Creating new Streamable HTTP transport");
+        Self {
+            sessions: Arc::new(HttpSessions::default()),
+            json_response: false,
+        }
+    }
+
+    /// Always answer requests with a plain JSON body, even if the client accepts SSE.
+    /// Server-initiated messages, including requests tools make of the client, then rely
+    /// on the client's `GET` stream.
+    pub fn with_json_response(mut self, json_response: bool) -> Self {
+        self.json_response = json_response;
+        self
+    }
+
+    pub fn create_router(server: Arc<McpServer>) -> Router {
+        Self::new().into_router(server)
+    }
+
+    pub fn into_router(self, server: Arc<McpServer>) -> Router {
+        info!("Creating Streamable HTTP router");
+        let state = Arc::new(self);
+
+        Router::new()
+            .route(
+                "/mcp",
+                post(
+                    |Extension(state): Extension<Arc<StreamableHttpServerTransport>>,
+                     Extension(server): Extension<Arc<McpServer>>,
+                     headers: HeaderMap,
+                     body: Bytes| async move {
+                        state.post_handler(server, headers, body).await
+                    },
+                )
+                .get(
+                    |Extension(state): Extension<Arc<StreamableHttpServerTransport>>,
+                     headers: HeaderMap| async move { state.get_handler(headers) },
+                )
+                .delete(
+                    |Extension(state): Extension<Arc<StreamableHttpServerTransport>>,
+                     Extension(server): Extension<Arc<McpServer>>,
+                     headers: HeaderMap| async move {
+                        state.delete_handler(server, headers)
+                    },
+                ),
+            )
+            .fallback(|req: axum::http::Request<axum::body::Body>| async move {
+                error!(
+                    method = %req.method(),
+                    uri = %req.uri(),
+                    "Request to unknown route"
+                );
+                StatusCode::NOT_FOUND
+            })
+            .layer(Extension(state))
+            .layer(Extension(server))
+    }
+
+    async fn post_handler(
+        self: Arc<Self>,
+        server: Arc<McpServer>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Response {
+        let message = match serde_json::from_slice(&body).and_then(IncomingMessage::from_value) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!(error = %e, "Failed to parse JSON-RPC message");
+                let response = JsonRpcResponse::Single(Output::Failure(Failure {
+                    jsonrpc: Some(Version::V2),
+                    error: Error::parse_error(),
+                    id: Id::Null,
+                }));
+                return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+            }
+        };
+
+        let is_initialize = matches!(
+            &message,
+            IncomingMessage::Request(JsonRpcRequest::Single(Call::MethodCall(call)))
+                if call.method == "initialize"
+        );
+
+        let session = match session_id(&headers) {
+            Some(session_id) => {
+                let session = server
+                    .session(session_id)
+                    .filter(|_| self.sessions.contains(session_id));
+                match session {
+                    Some(session) => session,
+                    None => {
+                        warn!(session_id = %session_id, "Session ID not found");
+                        return StatusCode::NOT_FOUND.into_response();
+                    }
+                }
+            }
+            None if is_initialize => self.sessions.open(&server),
+            None => {
+                error!("No Mcp-Session-Id header provided");
+                return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response();
+            }
+        };
+        let session_header = HeaderValue::from_str(session.id()).ok();
+
+        info!(session_id = %session.id(), "Received JSON-RPC message");
+
+        let request = match message {
+            IncomingMessage::Request(request) => request,
+            IncomingMessage::Response(response) => {
+                server.handle_session_response(&session, response);
+                return StatusCode::ACCEPTED.into_response();
+            }
+        };
+
+        if !expects_response(&request) {
+            let _ = Self::respond(&server, &session, request).await;
+            return StatusCode::ACCEPTED.into_response();
+        }
+
+        let mut response = if !self.json_response && accepts_event_stream(&headers) {
+            self.stream_response(server, session, request)
+        } else {
+            match Self::respond(&server, &session, request).await {
+                Some(response) => Json(response).into_response(),
+                None => StatusCode::ACCEPTED.into_response(),
+            }
+        };
+
+        if let (true, Some(value)) = (is_initialize, session_header) {
+            response.headers_mut().insert(SESSION_ID_HEADER, value);
+        }
+        response
+    }
+
+    /// Answers a request over a dedicated SSE stream which also receives the session's
+    /// server-initiated messages until the response has been sent. Messages sent while the
+    /// request was handled are written ahead of the response.
+    fn stream_response(
+        &self,
+        server: Arc<McpServer>,
+        session: Arc<Session>,
+        request: JsonRpcRequest,
+    ) -> Response {
+        let (tx, mut rx) = mpsc::channel(100);
+        self.sessions.push_stream(session.id(), tx.clone());
+
+        let sessions = self.sessions.clone();
+        tokio::spawn(async move {
+            if let Some(response) = Self::respond(&server, &session, request).await {
+                match serde_json::to_string(&response) {
+                    Ok(data) => sessions.respond(
+                        session.id(),
+                        &tx,
+                        Event::default().event("message").data(data),
+                    ),
+                    Err(e) => error!(error = %e, "Failed to serialize response"),
+                }
+            }
+            sessions.remove_stream(session.id(), &tx);
+        });
+
+        let stream = stream! {
+            while let Some(event) = rx.recv().await {
+                yield event;
+            }
+        };
+        Sse::new(stream).into_response()
+    }
+
+    fn get_handler(&self, headers: HeaderMap) -> Response {
+        if !accepts_event_stream(&headers) {
+            return StatusCode::NOT_ACCEPTABLE.into_response();
+        }
+        let Some(session_id) = session_id(&headers).map(str::to_string) else {
+            error!("No Mcp-Session-Id header provided");
+            return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response();
+        };
+
+        // The session owns the only sender, so the stream ends when the session is deleted;
+        // a client disconnect closes the channel and the sender is pruned on next use.
+        let (tx, mut rx) = mpsc::channel(100);
+        if !self.sessions.push_stream(&session_id, tx) {
+            warn!(session_id = %session_id, "Session ID not found");
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        info!(session_id = %session_id, "Opened server-initiated message stream");
+
+        let stream = stream! {
+            while let Some(event) = rx.recv().await {
+                yield event;
+            }
+        };
+        Sse::new(stream)
+            .keep_alive(KeepAlive::default())
+            .into_response()
+    }
+
+    fn delete_handler(&self, server: Arc<McpServer>, headers: HeaderMap) -> StatusCode {
+        match session_id(&headers) {
+            Some(session_id) if self.sessions.close(&server, session_id) => StatusCode::OK,
+            Some(session_id) => {
+                warn!(session_id = %session_id, "Session ID not found");
+                StatusCode::NOT_FOUND
+            }
+            None => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    async fn respond(
+        server: &McpServer,
+        session: &Arc<Session>,
+        request: JsonRpcRequest,
+    ) -> Option<JsonRpcResponse> {
+        let id = match &request {
+            JsonRpcRequest::Single(Call::MethodCall(call)) => call.id.clone(),
+            _ => Id::Null,
+        };
+        match server.handle_session_request(session, request).await {
+            Ok(response) => response,
+            // Cancelled requests get no response
+            Err(McpError::RequestCancelled) => None,
+            Err(e) => {
+                error!(session_id = %session.id(), error = %e, "Server request handler failed");
+                Some(JsonRpcResponse::Single(Output::Failure(Failure {
+                    jsonrpc: Some(Version::V2),
+                    error: e.into(),
+                    id,
+                })))
+            }
+        }
+    }
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("text/event-stream"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use offeryn_derive::tool;
+    use offeryn_types::{LoggingLevel, PeerError, RequestContext};
+    use serde_json::{json, Value};
+
+    #[derive(Default)]
+    struct Calculator {}
+
+    #[tool]
+    impl Calculator {
+        /// Add two numbers
+        async fn add(&self, a: i64, b: i64) -> i64 {
+            a + b
+        }
+    }
+
+    #[derive(Default)]
+    struct Workspace {}
+
+    #[tool]
+    impl Workspace {
+        /// Log progress, then report how many steps ran
+        async fn build(&self, ctx: RequestContext) -> usize {
+            ctx.log(LoggingLevel::Info, None, json!("building"));
+            3
+        }
+
+        /// List the client's roots
+        async fn roots(&self, ctx: RequestContext) -> Result<usize, String> {
+            ctx.roots()
+                .await
+                .map(|roots| roots.len())
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        format!("http://{}/mcp", addr)
+    }
+
+    async fn initialize(http: &reqwest::Client, url: &str) -> String {
+        let response = http
+            .post(url)
+            .header("accept", "application/json, text/event-stream")
+            .body(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": {}
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn tools_call() -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": "calculator_add", "arguments": { "a": 2, "b": 3 } }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_json_and_sse_responses() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Calculator::default()).await;
+        let url = serve(StreamableHttpServerTransport::create_router(server)).await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json")
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(tools_call())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["result"]["content"][0]["text"], "5");
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json, text/event-stream")
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(tools_call())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let body = response.text().await.unwrap();
+        let data = body
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap();
+        let message: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(message["id"], 2);
+        assert_eq!(message["result"]["content"][0]["text"], "5");
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }).to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Calculator::default()).await;
+        let url = serve(StreamableHttpServerTransport::create_router(server)).await;
+        let http = reqwest::Client::new();
+
+        let response = http.post(&url).body(tools_call()).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, "unknown")
+            .body(tools_call())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let session_id = initialize(&http, &url).await;
+        let response = http
+            .delete(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(tools_call())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_stream_notifications() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        let notifier = server.resource_notifier();
+        let url = serve(StreamableHttpServerTransport::create_router(server)).await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json")
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 2,
+                    "method": "resources/subscribe",
+                    "params": { "uri": "file:///log.txt" }
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let stream = http
+            .get(&url)
+            .header("accept", "text/event-stream")
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(stream.status(), reqwest::StatusCode::OK);
+        let mut body = stream.bytes_stream();
+
+        // The stream is attached once the response headers arrive
+        assert_eq!(notifier.notify_updated("file:///log.txt"), 1);
+
+        let mut received = String::new();
+        while !received.contains("\n\n") {
+            let chunk = body.next().await.unwrap().unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        let data = received
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap();
+        let notification: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(notification["method"], "notifications/resources/updated");
+    }
+
+    fn call_tool(name: &str) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": {} }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_sse_response_follows_session_messages() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Workspace::default()).await;
+        let url = serve(StreamableHttpServerTransport::create_router(server)).await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json, text/event-stream")
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(call_tool("workspace_build"))
+            .send()
+            .await
+            .unwrap();
+        let body = response.text().await.unwrap();
+        let messages: Vec<Value> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["method"], "notifications/message");
+        assert_eq!(messages[0]["params"]["data"], "building");
+        assert_eq!(messages[1]["id"], 2);
+        assert_eq!(messages[1]["result"]["content"][0]["text"], "3");
+    }
+
+    #[tokio::test]
+    async fn test_peer_requests_fail_without_stream() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Workspace::default()).await;
+        let router = StreamableHttpServerTransport::new()
+            .with_json_response(true)
+            .into_router(server);
+        let url = serve(router).await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json")
+            .body(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": { "capabilities": { "roots": {} } }
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .unwrap();
+        let session_id = response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // With neither an SSE response nor a GET stream, `roots/list` cannot reach the client
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            http.post(&url)
+                .header("accept", "application/json")
+                .header(SESSION_ID_HEADER, &session_id)
+                .body(call_tool("workspace_roots"))
+                .send(),
+        )
+        .await
+        .expect("peer request was left waiting")
+        .unwrap();
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["result"]["isError"], true);
+        assert_eq!(
+            body["result"]["content"][0]["text"],
+            PeerError::Disconnected.to_string()
+        );
+    }
+}
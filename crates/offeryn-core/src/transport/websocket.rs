@@ -0,0 +1,501 @@
+use super::IncomingMessage;
+use crate::server::Session;
+use crate::{McpError, McpServer};
+use axum::{
+    extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    http::StatusCode,
+    routing::get,
+    Extension, Router,
+};
+use futures::{SinkExt, StreamExt};
+use jsonrpc_core::{Call, Error, Failure, Id, Output, Request, Response, Version};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Close code sent when the peer stops answering pings (RFC 6455 "going away").
+const CLOSE_GOING_AWAY: u16 = 1001;
+/// Close code sent when a binary frame is not valid UTF-8 JSON.
+const CLOSE_INVALID_PAYLOAD: u16 = 1007;
+
+/// Serves MCP over WebSockets at `/ws`.
+///
+/// Every socket is its own session: JSON-RPC requests arrive as text frames, responses and
+/// server-initiated notifications are written back on the same socket. Method calls are
+/// handled concurrently, notifications in the order they arrive. The server pings the
+/// client periodically and closes the socket if pongs stop arriving.
+#[derive(Debug, Clone)]
+pub struct WebSocketServerTransport {
+    ping_interval: Duration,
+}
+
+impl Default for WebSocketServerTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebSocketServerTransport {
+    pub fn new() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how often the server pings the client. A client that has not answered within
+    /// two intervals is disconnected.
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self
+    }
+
+    pub fn create_router(server: Arc<McpServer>) -> Router {
+        Self::new().into_router(server)
+    }
+
+    pub fn into_router(self, server: Arc<McpServer>) -> Router {
+        info!("Creating WebSocket router");
+        let config = Arc::new(self);
+
+        Router::new()
+            .route(
+                "/ws",
+                get(
+                    |ws: WebSocketUpgrade,
+                     Extension(server): Extension<Arc<McpServer>>,
+                     Extension(config): Extension<Arc<WebSocketServerTransport>>| async move {
+                        info!("New WebSocket connection request received");
+                        ws.protocols(["mcp"])
+                            .on_upgrade(move |socket| config.handle_socket(socket, server))
+                    },
+                ),
+            )
+            .fallback(|req: axum::http::Request<axum::body::Body>| async move {
+                error!(
+                    method = %req.method(),
+                    uri = %req.uri(),
+                    "Request to unknown route"
+                );
+                StatusCode::NOT_FOUND
+            })
+            .layer(Extension(config))
+            .layer(Extension(server))
+    }
+
+    async fn handle_socket(self: Arc<Self>, socket: WebSocket, server: Arc<McpServer>) {
+        let session_id = Uuid::new_v4().to_string();
+        let (mut sink, mut stream) = socket.split();
+
+        let (tx, mut rx) = mpsc::channel::<Message>(100);
+        let (notification_tx, mut notification_rx) = mpsc::unbounded_channel();
+        let session = server.connect(session_id.clone(), notification_tx);
+        info!(session_id = %session_id, "WebSocket session established");
+
+        let last_pong = Arc::new(std::sync::Mutex::new(Instant::now()));
+
+        let writer = tokio::spawn({
+            let session_id = session_id.clone();
+            let last_pong = last_pong.clone();
+            let ping_interval = self.ping_interval;
+            async move {
+                let mut ping = interval(ping_interval);
+                ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                ping.tick().await;
+
+                loop {
+                    let message = tokio::select! {
+                        message = rx.recv() => match message {
+                            Some(message) => {
+                                // Whatever a request sent before finishing goes out ahead of
+                                // its response
+                                while let Ok(call) = notification_rx.try_recv() {
+                                    if let Some(text) = Self::serialize_call(&session_id, &call) {
+                                        if sink.send(Message::Text(text)).await.is_err() {
+                                            break;
+                                        }
+                                    }
+                                }
+                                message
+                            }
+                            None => break,
+                        },
+                        Some(call) = notification_rx.recv() => match Self::serialize_call(&session_id, &call) {
+                            Some(text) => Message::Text(text),
+                            None => continue,
+                        },
+                        _ = ping.tick() => {
+                            if last_pong.lock().unwrap().elapsed() > ping_interval * 2 {
+                                warn!(session_id = %session_id, "WebSocket keepalive timed out");
+                                let _ = sink
+                                    .send(Message::Close(Some(CloseFrame {
+                                        code: CLOSE_GOING_AWAY,
+                                        reason: "keepalive timeout".into(),
+                                    })))
+                                    .await;
+                                break;
+                            }
+                            Message::Ping(Vec::new())
+                        }
+                    };
+
+                    let is_close = matches!(message, Message::Close(_));
+                    if let Err(e) = sink.send(message).await {
+                        debug!(session_id = %session_id, error = %e, "WebSocket send failed");
+                        break;
+                    }
+                    if is_close {
+                        break;
+                    }
+                }
+            }
+        });
+
+        while let Some(message) = stream.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                    Ok(text) => text,
+                    Err(_) => {
+                        warn!(session_id = %session_id, "Received non UTF-8 binary frame");
+                        let _ = tx
+                            .send(Message::Close(Some(CloseFrame {
+                                code: CLOSE_INVALID_PAYLOAD,
+                                reason: "expected UTF-8 JSON".into(),
+                            })))
+                            .await;
+                        break;
+                    }
+                },
+                Ok(Message::Pong(_)) => {
+                    *last_pong.lock().unwrap() = Instant::now();
+                    continue;
+                }
+                // Pings are answered automatically by the protocol implementation
+                Ok(Message::Ping(_)) => continue,
+                Ok(Message::Close(frame)) => {
+                    match frame {
+                        Some(frame) => info!(
+                            session_id = %session_id,
+                            code = %frame.code,
+                            reason = %frame.reason,
+                            "Client closed WebSocket"
+                        ),
+                        None => info!(session_id = %session_id, "Client closed WebSocket"),
+                    }
+                    break;
+                }
+                Err(e) => {
+                    warn!(session_id = %session_id, error = %e, "WebSocket receive failed");
+                    break;
+                }
+            };
+
+            let message = serde_json::from_str(&text).and_then(IncomingMessage::from_value);
+            let request = match message {
+                Ok(IncomingMessage::Request(request)) => request,
+                Ok(IncomingMessage::Response(response)) => {
+                    server.handle_session_response(&session, response);
+                    continue;
+                }
+                Err(_) => {
+                    let error_response = Response::Single(Output::Failure(Failure {
+                        jsonrpc: Some(Version::V2),
+                        error: Error::parse_error(),
+                        id: Id::Null,
+                    }));
+                    if let Ok(text) = serde_json::to_string(&error_response) {
+                        let _ = tx.send(Message::Text(text)).await;
+                    }
+                    continue;
+                }
+            };
+
+            // Notifications are cheap and order-sensitive, so handle them inline
+            if let Request::Single(Call::Notification(_)) = &request {
+                Self::respond(&server, &session, request, &tx).await;
+                continue;
+            }
+
+            // Method calls get their own task so slow tools don't stall the socket
+            tokio::spawn({
+                let server = server.clone();
+                let session = session.clone();
+                let tx = tx.clone();
+                async move { Self::respond(&server, &session, request, &tx).await }
+            });
+        }
+
+        info!(session_id = %session_id, "WebSocket session closed");
+        server.disconnect(&session_id);
+        drop(session);
+        drop(tx);
+        let _ = writer.await;
+    }
+
+    fn serialize_call(session_id: &str, call: &Call) -> Option<String> {
+        serde_json::to_string(call)
+            .inspect_err(|e| {
+                error!(session_id = %session_id, error = %e, "Failed to serialize notification")
+            })
+            .ok()
+    }
+
+    /// Handles one request and writes its response, if any, to the socket.
+    async fn respond(
+        server: &McpServer,
+        session: &Arc<Session>,
+        request: Request,
+        tx: &mpsc::Sender<Message>,
+    ) {
+        let id = match &request {
+            Request::Single(Call::MethodCall(call)) => call.id.clone(),
+            _ => Id::Null,
+        };
+        let response = match server.handle_session_request(session, request).await {
+            Ok(Some(response)) => response,
+            // Notifications and cancelled requests get no response
+            Ok(None) | Err(McpError::RequestCancelled) => return,
+            Err(e) => Response::Single(Output::Failure(Failure {
+                jsonrpc: Some(Version::V2),
+                error: e.into(),
+                id,
+            })),
+        };
+        match serde_json::to_string(&response) {
+            Ok(text) => {
+                let _ = tx.send(Message::Text(text)).await;
+            }
+            Err(e) => error!(error = %e, "Failed to serialize response"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use offeryn_derive::tool;
+    use serde_json::{json, Value};
+    use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+    #[derive(Default)]
+    struct Calculator {}
+
+    #[tool]
+    impl Calculator {
+        /// Add two numbers
+        async fn add(&self, a: i64, b: i64) -> i64 {
+            a + b
+        }
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        format!("ws://{}/ws", addr)
+    }
+
+    async fn next_json<S>(stream: &mut S) -> Value
+    where
+        S: futures::Stream<Item = Result<ClientMessage, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+    {
+        loop {
+            match stream.next().await.unwrap().unwrap() {
+                ClientMessage::Text(text) => return serde_json::from_str(&text).unwrap(),
+                ClientMessage::Ping(_) | ClientMessage::Pong(_) => continue,
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+    }
+
+    async fn initialize<S>(socket: &mut S)
+    where
+        S: futures::Sink<ClientMessage>
+            + futures::Stream<Item = Result<ClientMessage, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+        S::Error: std::fmt::Debug,
+    {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": offeryn_types::LATEST_PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        socket
+            .send(ClientMessage::Text(request.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(next_json(socket).await["id"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_session() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Calculator::default()).await;
+        let url = serve(WebSocketServerTransport::create_router(server)).await;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        initialize(&mut socket).await;
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "calculator_add", "arguments": { "a": 2, "b": 3 } }
+        });
+        socket
+            .send(ClientMessage::Text(request.to_string()))
+            .await
+            .unwrap();
+
+        let response = next_json(&mut socket).await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["content"][0]["text"], "5");
+
+        // Notifications are not answered; the next frame belongs to the following request
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        socket
+            .send(ClientMessage::Text(notification.to_string()))
+            .await
+            .unwrap();
+        socket
+            .send(ClientMessage::Text("not json".to_string()))
+            .await
+            .unwrap();
+        let response = next_json(&mut socket).await;
+        assert_eq!(response["error"]["code"], -32700);
+
+        socket.close(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_websocket_notifications() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        let notifier = server.resource_notifier();
+        let url = serve(WebSocketServerTransport::create_router(server)).await;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        initialize(&mut socket).await;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "resources/subscribe",
+            "params": { "uri": "file:///log.txt" }
+        });
+        socket
+            .send(ClientMessage::Text(request.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(next_json(&mut socket).await["id"], 1);
+
+        assert_eq!(notifier.notify_updated("file:///log.txt"), 1);
+        let notification = next_json(&mut socket).await;
+        assert_eq!(notification["method"], "notifications/resources/updated");
+        assert_eq!(notification["params"]["uri"], "file:///log.txt");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_websocket_progress_precedes_response() {
+        use offeryn_types::{LoggingLevel, RequestContext};
+
+        #[derive(Default)]
+        struct Indexer {}
+
+        #[tool]
+        impl Indexer {
+            /// Reports completion right before returning
+            async fn index(&self, ctx: RequestContext) -> usize {
+                ctx.log(LoggingLevel::Info, None, json!("indexing"));
+                ctx.report_progress(1.0, Some(1.0), Some("indexed"));
+                1
+            }
+        }
+
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        server.register_tools(Indexer::default()).await;
+        let url = serve(WebSocketServerTransport::create_router(server)).await;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        initialize(&mut socket).await;
+        for id in 1..=20 {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "indexer_index", "arguments": {}, "_meta": { "progressToken": id } }
+            });
+            socket
+                .send(ClientMessage::Text(request.to_string()))
+                .await
+                .unwrap();
+        }
+
+        // Each call's progress is written before its response
+        let mut progressed = std::collections::HashSet::new();
+        let mut responses = 0;
+        while responses < 20 {
+            let message = next_json(&mut socket).await;
+            match message["method"].as_str() {
+                Some("notifications/progress") => {
+                    progressed.insert(message["params"]["progressToken"].as_i64().unwrap());
+                }
+                Some(_) => {}
+                None => {
+                    assert!(progressed.contains(&message["id"].as_i64().unwrap()));
+                    responses += 1;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_keepalive() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        let router = WebSocketServerTransport::new()
+            .with_ping_interval(Duration::from_millis(50))
+            .into_router(server);
+        let url = serve(router).await;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        match socket.next().await.unwrap().unwrap() {
+            ClientMessage::Ping(_) => {}
+            other => panic!("Expected ping, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_keepalive_timeout() {
+        let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+        let router = WebSocketServerTransport::new()
+            .with_ping_interval(Duration::from_millis(50))
+            .into_router(server);
+        let url = serve(router).await;
+
+        // Reading would answer the pings, so stay silent until the server has given up
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        let close = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match socket.next().await {
+                    Some(Ok(ClientMessage::Ping(_))) => continue,
+                    Some(Ok(ClientMessage::Close(frame))) => return frame,
+                    other => panic!("Expected close frame, got {:?}", other),
+                }
+            }
+        })
+        .await
+        .expect("server did not close the socket");
+        assert_eq!(u16::from(close.unwrap().code), CLOSE_GOING_AWAY);
+    }
+}
@@ -0,0 +1,285 @@
+use async_trait::async_trait;
+use offeryn_core::client::{SseClientTransport, StdioClientTransport};
+use offeryn_core::transport::{SseServerTransport, StdioServerTransport};
+use offeryn_core::{ClientError, McpClient, McpServer};
+use offeryn_derive::{prompt, tool};
+use offeryn_types::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::duplex;
+
+/// A simple calculator
+#[derive(Default)]
+struct Calculator {}
+
+#[tool]
+impl Calculator {
+    /// Add two numbers
+    async fn add(&self, a: i64, b: i64) -> i64 {
+        a + b
+    }
+
+    /// Divide two numbers
+    async fn divide(&self, a: i64, b: i64) -> Result<f64, String> {
+        if b == 0 {
+            Err("Cannot divide by zero".to_string())
+        } else {
+            Ok(a as f64 / b as f64)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Greeter {}
+
+#[prompt]
+impl Greeter {
+    /// Greet someone
+    /// # Parameters
+    /// * `name` - Who to greet
+    async fn hello(&self, name: String) -> String {
+        format!("Say hello to {}", name)
+    }
+}
+
+struct Motd;
+
+#[async_trait]
+impl McpResource for Motd {
+    fn uri(&self) -> &str {
+        "motd://today"
+    }
+
+    fn name(&self) -> &str {
+        "Message of the day"
+    }
+
+    async fn read(&self) -> Result<Vec<ResourceContents>, String> {
+        Ok(vec![ResourceContents::text(self.uri(), None, "Hello!")])
+    }
+}
+
+async fn connect(server: Arc<McpServer>) -> McpClient {
+    let (client_reader, server_writer) = duplex(4096);
+    let (server_reader, client_writer) = duplex(4096);
+
+    let transport = StdioServerTransport::with_streams(server, server_reader, server_writer);
+    tokio::spawn(async move {
+        transport.run().await.unwrap();
+    });
+
+    McpClient::connect(
+        StdioClientTransport::with_streams(client_reader, client_writer),
+        "test-client",
+        "1.0.0",
+    )
+    .await
+    .unwrap()
+}
+
+async fn test_server() -> Arc<McpServer> {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tools(Calculator::default()).await;
+    server.register_prompts(Greeter::default()).await;
+    server.register_resource(Motd).await;
+    server
+}
+
+#[tokio::test]
+async fn test_client_initialize() {
+    let client = connect(test_server().await).await;
+
+    assert_eq!(client.server().server_info.name, "test-server");
+    assert_eq!(client.server().protocol_version, LATEST_PROTOCOL_VERSION);
+    assert!(client.server().capabilities.resources.is_some());
+    assert!(client.server().capabilities.prompts.is_some());
+}
+
+#[tokio::test]
+async fn test_client_tools() {
+    let client = connect(test_server().await).await;
+
+    let tools = client.list_tools().await.unwrap();
+    let mut names: Vec<_> = tools.tools.iter().map(|t| t.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["calculator_add", "calculator_divide"]);
+
+    // Issue requests concurrently to exercise id correlation
+    let (sum, quotient) = tokio::join!(
+        client.call_tool("calculator_add", json!({ "a": 2, "b": 3 })),
+        client.call_tool("calculator_divide", json!({ "a": 1, "b": 0 })),
+    );
+    match &sum.unwrap().content[0] {
+        Content::Text { text } => assert_eq!(text, "5"),
+        _ => panic!("Expected text content"),
+    }
+    assert_eq!(quotient.unwrap().is_error, Some(true));
+}
+
+#[tokio::test]
+async fn test_client_resources_and_prompts() {
+    let server = test_server().await;
+    let notifier = server.resource_notifier();
+    let client = connect(server).await;
+
+    let resources = client.list_resources().await.unwrap();
+    assert_eq!(resources.resources[0].uri, "motd://today");
+
+    let contents = client.read_resource("motd://today").await.unwrap();
+    assert_eq!(
+        contents.contents,
+        vec![ResourceContents::text("motd://today", None, "Hello!")]
+    );
+
+    let missing = client.read_resource("motd://yesterday").await;
+    assert!(matches!(missing, Err(ClientError::Rpc(e)) if e.code.code() == -32002));
+
+    let prompts = client.list_prompts().await.unwrap();
+    assert_eq!(prompts.prompts[0].name, "greeter_hello");
+
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "Ava".to_string());
+    let prompt = client.get_prompt("greeter_hello", args).await.unwrap();
+    assert_eq!(prompt.messages.len(), 1);
+    assert_eq!(prompt.messages[0].role, Role::User);
+
+    let mut notifications = client.notifications();
+    client.subscribe_resource("motd://today").await.unwrap();
+    assert_eq!(notifier.notify_updated("motd://today"), 1);
+
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.method, "notifications/resources/updated");
+}
+
+#[tokio::test]
+async fn test_sse_client_transport() {
+    let app = SseServerTransport::create_router(test_server().await);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = McpClient::connect(
+        SseClientTransport::new(&format!("http://{}/sse", addr)),
+        "test-client",
+        "1.0.0",
+    )
+    .await
+    .unwrap();
+    assert_eq!(client.server().server_info.name, "test-server");
+
+    let result = client
+        .call_tool("calculator_add", json!({ "a": 4, "b": 5 }))
+        .await
+        .unwrap();
+    match &result.content[0] {
+        Content::Text { text } => assert_eq!(text, "9"),
+        _ => panic!("Expected text content"),
+    }
+
+    // Errors are delivered over the event stream as well
+    let missing = client.call_tool("calculator_missing", json!({})).await;
+    assert!(matches!(missing, Err(ClientError::Rpc(_))));
+}
+
+#[tokio::test]
+async fn test_client_rejects_unsupported_protocol_version() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (client_reader, mut server_writer) = duplex(4096);
+    let (server_reader, client_writer) = duplex(4096);
+
+    // A server that answers `initialize` with a version the client does not speak
+    let server = tokio::spawn(async move {
+        let mut lines = BufReader::new(server_reader).lines();
+        let request: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "protocolVersion": "1999-01-01",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "ancient-server", "version": "0.1.0" }
+            }
+        });
+        server_writer
+            .write_all(format!("{}\n", response).as_bytes())
+            .await
+            .unwrap();
+        // Keep the connection open until the client hangs up
+        while let Ok(Some(_)) = lines.next_line().await {}
+    });
+
+    let result = McpClient::connect(
+        StdioClientTransport::with_streams(client_reader, client_writer),
+        "test-client",
+        "1.0.0",
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(ClientError::UnsupportedProtocolVersion(version)) if version == "1999-01-01"
+    ));
+
+    // The failed client must not keep the connection open
+    tokio::time::timeout(std::time::Duration::from_secs(5), server)
+        .await
+        .expect("connection leaked after failed handshake")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_client_answers_ping() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (client_reader, mut server_writer) = duplex(4096);
+    let (server_reader, client_writer) = duplex(4096);
+
+    // A server that pings the client right after the handshake
+    let server = tokio::spawn(async move {
+        let mut lines = BufReader::new(server_reader).lines();
+        let request: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "protocolVersion": LATEST_PROTOCOL_VERSION,
+                "capabilities": {},
+                "serverInfo": { "name": "pinging-server", "version": "0.1.0" }
+            }
+        });
+        let ping = json!({ "jsonrpc": "2.0", "id": "ping-1", "method": "ping" });
+        for message in [response, ping] {
+            server_writer
+                .write_all(format!("{}\n", message).as_bytes())
+                .await
+                .unwrap();
+        }
+        // Skip `notifications/initialized` and wait for the answer to the ping
+        loop {
+            let message: serde_json::Value =
+                serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            if message.get("method").is_none() {
+                return message;
+            }
+        }
+    });
+
+    let _client = McpClient::connect(
+        StdioClientTransport::with_streams(client_reader, client_writer),
+        "test-client",
+        "1.0.0",
+    )
+    .await
+    .unwrap();
+
+    let response = server.await.unwrap();
+    assert_eq!(
+        response,
+        json!({ "jsonrpc": "2.0", "id": "ping-1", "result": {} })
+    );
+}
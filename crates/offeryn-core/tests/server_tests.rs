@@ -1,10 +1,11 @@
 use async_trait::async_trait;
-use jsonrpc_core::{Call, Id, MethodCall, Output, Params, Version};
-use offeryn_core::{McpError, McpServer};
+use jsonrpc_core::{Call, Id, MethodCall, Notification, Output, Params, Version};
+use offeryn_core::{McpError, McpServer, Session, SessionState};
 use offeryn_types::McpTool;
 use offeryn_types::*;
 use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Duration;
 
 // Mock tool for testing
 struct MockTool;
@@ -30,7 +31,7 @@ impl McpTool for MockTool {
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<ToolResult, String> {
+    async fn execute(&self, args: Value, _ctx: RequestContext) -> Result<ToolResult, ToolError> {
         let echo = args["echo"].as_str().ok_or("Missing echo parameter")?;
         Ok(ToolResult {
             content: vec![ToolContent {
@@ -38,6 +39,7 @@ impl McpTool for MockTool {
                 r#type: "text".to_string(),
             }],
             is_error: false,
+            structured_content: None,
         })
     }
 }
@@ -54,7 +56,7 @@ async fn test_tools_list() {
         params: Params::None,
     }));
 
-    let response = server.handle_request(request).await.unwrap();
+    let response = server.handle_request(request).await.unwrap().unwrap();
 
     match response {
         JsonRpcResponse::Single(Output::Success(success)) => {
@@ -90,7 +92,7 @@ async fn test_tool_execution() {
         params: Params::Map(params),
     }));
 
-    let response = server.handle_request(request).await.unwrap();
+    let response = server.handle_request(request).await.unwrap().unwrap();
 
     match response {
         JsonRpcResponse::Single(Output::Success(success)) => {
@@ -99,6 +101,7 @@ async fn test_tool_execution() {
             match &result.content[0] {
                 Content::Text { text } => assert_eq!(text, "Hello, World!"),
                 Content::Image { .. } => panic!("Expected text content"),
+                Content::Audio { .. } => panic!("Expected text content"),
                 Content::EmbeddedResource { .. } => panic!("Expected text content"),
             }
             assert_eq!(result.is_error, Some(false));
@@ -123,8 +126,125 @@ async fn test_unknown_tool() {
         params: Params::Map(params),
     }));
 
-    let response = server.handle_request(request).await;
-    assert!(matches!(response, Err(McpError::MethodNotFound)));
+    // Unknown tools are invalid params, not unknown methods
+    let error = server.handle_request(request).await.unwrap_err();
+    assert!(matches!(error, McpError::InvalidParams { .. }));
+    assert_eq!(error.to_string(), "Unknown tool: non_existent_tool");
+
+    let error = jsonrpc_core::Error::from(error);
+    assert_eq!(error.code, jsonrpc_core::ErrorCode::InvalidParams);
+    assert_eq!(error.data, Some(json!({ "tool": "non_existent_tool" })));
+}
+
+#[tokio::test]
+async fn test_error_details() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(MockTool).await;
+
+    // Malformed params name the offending field
+    let error = server
+        .handle_request(method_call("tools/call", json!({ "name": 42 })))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, McpError::InvalidParams { .. }));
+    assert!(error
+        .to_string()
+        .starts_with("Invalid params for tools/call"));
+    assert_eq!(error.data(), Some(&json!({ "path": "name" })));
+
+    // A failing tool's message reaches the client as a tool result
+    let response = server
+        .handle_request(method_call(
+            "tools/call",
+            json!({ "name": "mock_tool", "arguments": {} }),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: CallToolResult = serde_json::from_value(success.result).unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert!(
+                matches!(&result.content[0], Content::Text { text } if text == "Missing echo parameter")
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+/// Fails in the way its `kind` argument asks for
+struct FailingTool;
+
+#[async_trait]
+impl McpTool for FailingTool {
+    fn name(&self) -> &str {
+        "fail"
+    }
+
+    fn description(&self) -> &str {
+        "Always fails"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": { "kind": { "type": "string" } } })
+    }
+
+    async fn execute(&self, args: Value, _ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        match args["kind"].as_str() {
+            Some("execution") => Err(ToolError::execution("Quota exceeded")
+                .with_structured_content(json!({ "retryAfter": 30 }))),
+            Some("io") => Err(std::io::Error::other("Disk full").into()),
+            _ => Err(ToolError::invalid_arguments("kind must be execution or io")),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tool_errors() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(FailingTool).await;
+    let call = |kind: &str| {
+        server.handle_request(method_call(
+            "tools/call",
+            json!({ "name": "fail", "arguments": { "kind": kind } }),
+        ))
+    };
+
+    // Execution errors are results the model can see
+    match call("execution").await.unwrap().unwrap() {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(
+                success.result,
+                json!({
+                    "content": [{ "type": "text", "text": "Quota exceeded" }],
+                    "isError": true,
+                    "structuredContent": { "retryAfter": 30 }
+                })
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+    match call("io").await.unwrap().unwrap() {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(success.result["isError"], true);
+            assert_eq!(success.result["content"][0]["text"], "Disk full");
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    // Protocol errors are JSON-RPC errors
+    match call("other").await.unwrap().unwrap() {
+        JsonRpcResponse::Single(Output::Failure(failure)) => {
+            assert_eq!(failure.error.code, jsonrpc_core::ErrorCode::InvalidParams);
+            assert_eq!(
+                failure.error.message,
+                "Invalid arguments: kind must be execution or io"
+            );
+            assert_eq!(failure.error.data, Some(json!({ "tool": "fail" })));
+        }
+        _ => panic!("Expected failure response"),
+    }
 }
 
 #[tokio::test]
@@ -138,7 +258,7 @@ async fn test_invalid_method() {
         params: Params::None,
     }));
 
-    let response = server.handle_request(request).await.unwrap();
+    let response = server.handle_request(request).await.unwrap().unwrap();
 
     match response {
         JsonRpcResponse::Single(Output::Failure(failure)) => {
@@ -146,7 +266,11 @@ async fn test_invalid_method() {
                 failure.error.code.code(),
                 jsonrpc_core::ErrorCode::MethodNotFound.code()
             );
-            assert_eq!(failure.error.message, "Method not found");
+            assert_eq!(failure.error.message, "Method not found: invalid/method");
+            assert_eq!(
+                failure.error.data,
+                Some(json!({ "method": "invalid/method" }))
+            );
         }
         _ => panic!("Expected failure response"),
     }
@@ -164,7 +288,7 @@ async fn test_initialize() {
         params: Params::None,
     }));
 
-    let response = server.handle_request(request).await.unwrap();
+    let response = server.handle_request(request).await.unwrap().unwrap();
 
     match response {
         JsonRpcResponse::Single(Output::Success(success)) => {
@@ -172,9 +296,2274 @@ async fn test_initialize() {
             assert_eq!(result.server_info.name, "test-server");
             assert_eq!(result.server_info.version, "1.0.0");
             assert_eq!(result.protocol_version, LATEST_PROTOCOL_VERSION);
-            assert_eq!(result.capabilities.tools.len(), 1);
-            assert!(result.capabilities.tools.contains_key("mock_tool"));
+            let tools = result.capabilities.tools.unwrap();
+            assert_eq!(tools.list_changed, Some(true));
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+struct ConfigResource;
+
+#[async_trait]
+impl McpResource for ConfigResource {
+    fn uri(&self) -> &str {
+        "config://app"
+    }
+
+    fn name(&self) -> &str {
+        "App config"
+    }
+
+    fn mime_type(&self) -> Option<&str> {
+        Some("application/toml")
+    }
+
+    async fn read(&self) -> Result<Vec<ResourceContents>, String> {
+        Ok(vec![ResourceContents::text(
+            self.uri(),
+            self.mime_type(),
+            "debug = true",
+        )])
+    }
+}
+
+struct UserAvatarTemplate;
+
+#[async_trait]
+impl McpResourceTemplate for UserAvatarTemplate {
+    fn uri_template(&self) -> &str {
+        "users://{id}/avatar"
+    }
+
+    fn name(&self) -> &str {
+        "User avatar"
+    }
+
+    async fn read(
+        &self,
+        uri: &str,
+        variables: std::collections::HashMap<String, String>,
+    ) -> Result<Vec<ResourceContents>, String> {
+        let id = &variables["id"];
+        Ok(vec![ResourceContents::blob(
+            uri,
+            Some("image/png"),
+            id.as_bytes(),
+        )])
+    }
+}
+
+fn method_call(method: &str, params: Value) -> JsonRpcRequest {
+    JsonRpcRequest::Single(Call::MethodCall(MethodCall {
+        jsonrpc: Some(Version::V2),
+        id: Id::Num(1),
+        method: method.to_string(),
+        params: match params {
+            Value::Object(map) => Params::Map(map),
+            _ => Params::None,
+        },
+    }))
+}
+
+#[tokio::test]
+async fn test_resources_list_and_templates() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_resource(ConfigResource).await;
+    server.register_resource_template(UserAvatarTemplate).await;
+
+    let response = server
+        .handle_request(method_call("resources/list", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: ListResourcesResult = serde_json::from_value(success.result).unwrap();
+            assert_eq!(result.resources.len(), 1);
+            assert_eq!(result.resources[0].uri, "config://app");
+            assert_eq!(
+                result.resources[0].mime_type.as_deref(),
+                Some("application/toml")
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    let response = server
+        .handle_request(method_call("resources/templates/list", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: ListResourceTemplatesResult =
+                serde_json::from_value(success.result).unwrap();
+            assert_eq!(result.resource_templates.len(), 1);
+            assert_eq!(
+                result.resource_templates[0].uri_template,
+                "users://{id}/avatar"
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+#[tokio::test]
+async fn test_resources_read() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_resource(ConfigResource).await;
+    server.register_resource_template(UserAvatarTemplate).await;
+
+    let response = server
+        .handle_request(method_call(
+            "resources/read",
+            json!({ "uri": "config://app" }),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(
+                success.result,
+                json!({
+                    "contents": [{
+                        "uri": "config://app",
+                        "mimeType": "application/toml",
+                        "text": "debug = true"
+                    }]
+                })
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    let response = server
+        .handle_request(method_call(
+            "resources/read",
+            json!({ "uri": "users://42/avatar" }),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: ReadResourceResult = serde_json::from_value(success.result).unwrap();
+            assert_eq!(
+                result.contents,
+                vec![ResourceContents::Blob {
+                    uri: "users://42/avatar".to_string(),
+                    mime_type: Some("image/png".to_string()),
+                    blob: "NDI=".to_string(),
+                }]
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    let response = server
+        .handle_request(method_call(
+            "resources/read",
+            json!({ "uri": "config://missing" }),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Failure(failure)) => {
+            assert_eq!(failure.error.code.code(), -32002);
+            assert_eq!(
+                failure.error.data,
+                Some(json!({ "uri": "config://missing" }))
+            );
+        }
+        _ => panic!("Expected failure response"),
+    }
+}
+
+/// Completes a read only once `parties` reads are running at the same time
+struct RendezvousResource {
+    barrier: Arc<tokio::sync::Barrier>,
+}
+
+#[async_trait]
+impl McpResource for RendezvousResource {
+    fn uri(&self) -> &str {
+        "rendezvous://static"
+    }
+
+    fn name(&self) -> &str {
+        "Rendezvous"
+    }
+
+    async fn read(&self) -> Result<Vec<ResourceContents>, String> {
+        self.barrier.wait().await;
+        Ok(vec![ResourceContents::text(self.uri(), None, "met")])
+    }
+}
+
+#[async_trait]
+impl McpResourceTemplate for RendezvousResource {
+    fn uri_template(&self) -> &str {
+        "rendezvous://dynamic/{id}"
+    }
+
+    fn name(&self) -> &str {
+        "Rendezvous template"
+    }
+
+    async fn read(
+        &self,
+        uri: &str,
+        _variables: std::collections::HashMap<String, String>,
+    ) -> Result<Vec<ResourceContents>, String> {
+        self.barrier.wait().await;
+        Ok(vec![ResourceContents::text(uri, None, "met")])
+    }
+}
+
+#[tokio::test]
+async fn test_resource_reads_run_in_parallel() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    let barrier = Arc::new(tokio::sync::Barrier::new(4));
+    server
+        .register_resource(RendezvousResource {
+            barrier: barrier.clone(),
+        })
+        .await;
+    server
+        .register_resource_template(RendezvousResource { barrier })
+        .await;
+
+    // Would deadlock if a registry were locked for the duration of a read
+    let uris = [
+        "rendezvous://static",
+        "rendezvous://static",
+        "rendezvous://dynamic/1",
+        "rendezvous://dynamic/2",
+    ];
+    let reads = uris
+        .iter()
+        .map(|uri| server.handle_request(method_call("resources/read", json!({ "uri": uri }))));
+    let responses = tokio::time::timeout(Duration::from_secs(5), futures::future::join_all(reads))
+        .await
+        .expect("resource reads were serialized");
+    for response in responses {
+        assert!(matches!(
+            response.unwrap().unwrap(),
+            JsonRpcResponse::Single(Output::Success(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_resources_capability() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+
+    let response = server
+        .handle_request(method_call("initialize", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert!(success.result["capabilities"].get("resources").is_none());
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    server.register_resource(ConfigResource).await;
+    let response = server
+        .handle_request(method_call("initialize", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert!(success.result["capabilities"]["resources"].is_object());
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+#[tokio::test]
+async fn test_capabilities_follow_registrations() {
+    let server = McpServer::new("test-server", "1.0.0")
+        .with_experimental_capability("batching", json!({ "maxSize": 10 }));
+
+    let response = server
+        .handle_request(method_call("initialize", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(
+                success.result["capabilities"],
+                json!({
+                    // Tools may be registered later, so they are advertised regardless
+                    "tools": { "listChanged": true },
+                    "logging": {},
+                    "experimental": { "batching": { "maxSize": 10 } }
+                })
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    server.register_tool(MockTool).await;
+    server.register_prompt(GreetingPrompt).await;
+    let capabilities = server.capabilities().await;
+    assert_eq!(capabilities.tools.unwrap().list_changed, Some(true));
+    assert!(capabilities.prompts.is_some());
+    assert!(capabilities.resources.is_none());
+    assert!(capabilities.logging.is_some());
+    assert!(capabilities.completions.is_none());
+}
+
+#[tokio::test]
+async fn test_resource_subscriptions() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_resource(ConfigResource).await;
+    let notifier = server.resource_notifier();
+
+    let (subscribed_tx, mut subscribed_rx) = tokio::sync::mpsc::unbounded_channel();
+    let subscribed = server.connect("subscribed".to_string(), subscribed_tx);
+    let (other_tx, mut other_rx) = tokio::sync::mpsc::unbounded_channel();
+    let _other = server.connect("other".to_string(), other_tx);
+    server
+        .handle_session_request(&subscribed, method_call("initialize", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let response = server
+        .handle_session_request(
+            &subscribed,
+            method_call("resources/subscribe", json!({ "uri": "config://app" })),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        response,
+        JsonRpcResponse::Single(Output::Success(_))
+    ));
+
+    assert_eq!(notifier.notify_updated("config://app"), 1);
+    assert_eq!(notifier.notify_updated("config://other"), 0);
+
+    match subscribed_rx.try_recv().unwrap() {
+        Call::Notification(notification) => {
+            assert_eq!(notification.method, "notifications/resources/updated");
+            assert_eq!(
+                notification.params,
+                Params::Map(
+                    json!({ "uri": "config://app" })
+                        .as_object()
+                        .unwrap()
+                        .clone()
+                )
+            );
+        }
+        _ => panic!("Expected notification"),
+    }
+    assert!(subscribed_rx.try_recv().is_err());
+    assert!(other_rx.try_recv().is_err());
+
+    server
+        .handle_session_request(
+            &subscribed,
+            method_call("resources/unsubscribe", json!({ "uri": "config://app" })),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(notifier.notify_updated("config://app"), 0);
+}
+
+#[tokio::test]
+async fn test_subscribe_requires_session() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+
+    let response = server
+        .handle_request(method_call(
+            "resources/subscribe",
+            json!({ "uri": "config://app" }),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Failure(failure)) => {
+            assert_eq!(
+                failure.error.code.code(),
+                jsonrpc_core::ErrorCode::InvalidRequest.code()
+            );
+        }
+        _ => panic!("Expected failure response"),
+    }
+}
+
+struct GreetingPrompt;
+
+#[async_trait]
+impl McpPrompt for GreetingPrompt {
+    fn name(&self) -> &str {
+        "greeting"
+    }
+
+    fn description(&self) -> &str {
+        "Greet someone by name"
+    }
+
+    fn arguments(&self) -> Vec<PromptArgument> {
+        vec![PromptArgument {
+            name: "name".to_string(),
+            description: Some("Who to greet".to_string()),
+            required: Some(true),
+        }]
+    }
+
+    async fn get(
+        &self,
+        args: std::collections::HashMap<String, String>,
+    ) -> Result<Vec<PromptMessage>, String> {
+        Ok(vec![PromptMessage::user(format!(
+            "Say hello to {}",
+            args["name"]
+        ))])
+    }
+}
+
+#[tokio::test]
+async fn test_prompts() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_prompt(GreetingPrompt).await;
+
+    let response = server
+        .handle_request(method_call("prompts/list", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: ListPromptsResult = serde_json::from_value(success.result).unwrap();
+            assert_eq!(result.prompts.len(), 1);
+            assert_eq!(result.prompts[0].name, "greeting");
+            assert_eq!(result.prompts[0].arguments.as_ref().unwrap().len(), 1);
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    let response = server
+        .handle_request(method_call(
+            "prompts/get",
+            json!({ "name": "greeting", "arguments": { "name": "Ava" } }),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(
+                success.result,
+                json!({
+                    "description": "Greet someone by name",
+                    "messages": [{
+                        "role": "user",
+                        "content": { "type": "text", "text": "Say hello to Ava" }
+                    }]
+                })
+            );
         }
         _ => panic!("Expected successful response"),
     }
+
+    for params in [
+        json!({ "name": "greeting" }),
+        json!({ "name": "missing", "arguments": {} }),
+    ] {
+        let response = server
+            .handle_request(method_call("prompts/get", params))
+            .await
+            .unwrap()
+            .unwrap();
+        match response {
+            JsonRpcResponse::Single(Output::Failure(failure)) => {
+                assert_eq!(
+                    failure.error.code.code(),
+                    jsonrpc_core::ErrorCode::InvalidParams.code()
+                );
+            }
+            _ => panic!("Expected failure response"),
+        }
+    }
+}
+
+/// A tool using features from newer protocol revisions
+/// Renders only once `parties` renders are running at the same time
+struct RendezvousPrompt {
+    barrier: Arc<tokio::sync::Barrier>,
+}
+
+#[async_trait]
+impl McpPrompt for RendezvousPrompt {
+    fn name(&self) -> &str {
+        "rendezvous"
+    }
+
+    fn description(&self) -> &str {
+        "Waits for other callers"
+    }
+
+    fn arguments(&self) -> Vec<PromptArgument> {
+        vec![]
+    }
+
+    async fn get(
+        &self,
+        _args: std::collections::HashMap<String, String>,
+    ) -> Result<Vec<PromptMessage>, String> {
+        self.barrier.wait().await;
+        Ok(vec![PromptMessage::user("met")])
+    }
+}
+
+#[tokio::test]
+async fn test_prompt_gets_run_in_parallel() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server
+        .register_prompt(RendezvousPrompt {
+            barrier: Arc::new(tokio::sync::Barrier::new(3)),
+        })
+        .await;
+
+    // Would deadlock if the registry were locked while a prompt renders
+    let gets = (0..3).map(|_| {
+        server.handle_request(method_call("prompts/get", json!({ "name": "rendezvous" })))
+    });
+    let responses = tokio::time::timeout(Duration::from_secs(5), futures::future::join_all(gets))
+        .await
+        .expect("prompt renders were serialized");
+    for response in responses {
+        assert!(matches!(
+            response.unwrap().unwrap(),
+            JsonRpcResponse::Single(Output::Success(_))
+        ));
+    }
+}
+
+struct WeatherTool;
+
+#[async_trait]
+impl McpTool for WeatherTool {
+    fn name(&self) -> &str {
+        "weather"
+    }
+
+    fn description(&self) -> &str {
+        "Current weather"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("Weather")
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        Some(ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": { "temperature": { "type": "number" } }
+        }))
+    }
+
+    async fn execute(&self, _args: Value, _ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: r#"{"temperature":21.5}"#.to_string(),
+            }],
+            is_error: false,
+            structured_content: Some(json!({ "temperature": 21.5 })),
+        })
+    }
+}
+
+async fn initialize_session(
+    server: &McpServer,
+    id: &str,
+    protocol_version: &str,
+) -> (Arc<Session>, String) {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect(id.to_string(), tx);
+    let response = server
+        .handle_session_request(
+            &session,
+            method_call(
+                "initialize",
+                json!({
+                    "protocolVersion": protocol_version,
+                    "capabilities": {},
+                    "clientInfo": { "name": "test-client", "version": "1.0.0" }
+                }),
+            ),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: InitializeResult = serde_json::from_value(success.result).unwrap();
+            (session, result.protocol_version)
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+#[tokio::test]
+async fn test_protocol_version_negotiation() {
+    let server = McpServer::new("test-server", "1.0.0");
+
+    for version in SUPPORTED_PROTOCOL_VERSIONS {
+        let (session, negotiated) = initialize_session(&server, version, version).await;
+        assert_eq!(negotiated, *version);
+        assert_eq!(session.protocol_version(), Some(*version));
+    }
+
+    let (session, negotiated) = initialize_session(&server, "future", "2099-01-01").await;
+    assert_eq!(negotiated, LATEST_PROTOCOL_VERSION);
+    assert_eq!(session.protocol_version(), Some(LATEST_PROTOCOL_VERSION));
+}
+
+#[tokio::test]
+async fn test_features_gated_on_protocol_version() {
+    let server = McpServer::new("test-server", "1.0.0");
+    server.register_tool(WeatherTool).await;
+
+    let success = |response| match response {
+        JsonRpcResponse::Single(Output::Success(success)) => success.result,
+        _ => panic!("Expected successful response"),
+    };
+
+    let (old, _) = initialize_session(&server, "old", PROTOCOL_VERSION_2024_11_05).await;
+    let (mid, _) = initialize_session(&server, "mid", PROTOCOL_VERSION_2025_03_26).await;
+    let (new, _) = initialize_session(&server, "new", PROTOCOL_VERSION_2025_06_18).await;
+
+    let list =
+        |session| server.handle_session_request(session, method_call("tools/list", json!({})));
+    let old_tools = success(list(&old).await.unwrap().unwrap());
+    assert_eq!(
+        old_tools["tools"][0],
+        json!({
+            "name": "weather",
+            "description": "Current weather",
+            "inputSchema": { "type": "object", "properties": {} }
+        })
+    );
+
+    let mid_tools = success(list(&mid).await.unwrap().unwrap());
+    assert_eq!(
+        mid_tools["tools"][0]["annotations"],
+        json!({ "readOnlyHint": true })
+    );
+    assert!(mid_tools["tools"][0].get("title").is_none());
+    assert!(mid_tools["tools"][0].get("outputSchema").is_none());
+
+    let new_tools = success(list(&new).await.unwrap().unwrap());
+    assert_eq!(new_tools["tools"][0]["title"], "Weather");
+    assert_eq!(new_tools["tools"][0]["outputSchema"]["type"], "object");
+
+    let call = |session| {
+        server.handle_session_request(
+            session,
+            method_call("tools/call", json!({ "name": "weather", "arguments": {} })),
+        )
+    };
+    let old_result = success(call(&old).await.unwrap().unwrap());
+    assert!(old_result.get("structuredContent").is_none());
+    let new_result = success(call(&new).await.unwrap().unwrap());
+    assert_eq!(
+        new_result["structuredContent"],
+        json!({ "temperature": 21.5 })
+    );
+}
+
+#[tokio::test]
+async fn test_session_lifecycle() {
+    let server = McpServer::new("test-server", "1.0.0");
+    server.register_tool(MockTool).await;
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("lifecycle".to_string(), tx);
+    assert_eq!(session.state(), SessionState::Uninitialized);
+
+    let call_tool = || {
+        method_call(
+            "tools/call",
+            json!({ "name": "mock_tool", "arguments": { "echo": "hi" } }),
+        )
+    };
+
+    // Only pings are allowed before initialize
+    let response = server
+        .handle_session_request(&session, call_tool())
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Failure(failure)) => {
+            assert_eq!(failure.error.code, jsonrpc_core::ErrorCode::InvalidRequest);
+        }
+        _ => panic!("Expected failure response"),
+    }
+    let response = server
+        .handle_session_request(&session, method_call("ping", Value::Null))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        response,
+        JsonRpcResponse::Single(Output::Success(_))
+    ));
+
+    let initialize = || {
+        method_call(
+            "initialize",
+            json!({
+                "protocolVersion": LATEST_PROTOCOL_VERSION,
+                "capabilities": {
+                    "experimental": {},
+                    "sampling": {},
+                    "roots": { "listChanged": true }
+                },
+                "clientInfo": { "name": "test-client", "version": "2.1.0" }
+            }),
+        )
+    };
+    let response = server
+        .handle_session_request(&session, initialize())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        response,
+        JsonRpcResponse::Single(Output::Success(_))
+    ));
+    assert_eq!(session.state(), SessionState::Initializing);
+    let client_info = session.client_info().unwrap();
+    assert_eq!(client_info.name, "test-client");
+    assert_eq!(client_info.version, "2.1.0");
+    let capabilities = session.client_capabilities().unwrap();
+    assert_eq!(capabilities.roots.unwrap().list_changed, Some(true));
+
+    // A second initialize is rejected
+    let response = server
+        .handle_session_request(&session, initialize())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        response,
+        JsonRpcResponse::Single(Output::Failure(_))
+    ));
+
+    // Notifications are never answered
+    let response = server
+        .handle_session_request(
+            &session,
+            JsonRpcRequest::Single(Call::Notification(Notification {
+                jsonrpc: Some(Version::V2),
+                method: "notifications/initialized".to_string(),
+                params: Params::None,
+            })),
+        )
+        .await
+        .unwrap();
+    assert!(response.is_none());
+    assert_eq!(session.state(), SessionState::Ready);
+
+    let response = server
+        .handle_session_request(&session, call_tool())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        response,
+        JsonRpcResponse::Single(Output::Success(_))
+    ));
+}
+
+/// Reports which client invoked it
+struct WhoAmITool;
+
+#[async_trait]
+impl McpTool for WhoAmITool {
+    fn name(&self) -> &str {
+        "whoami"
+    }
+
+    fn description(&self) -> &str {
+        "Names the calling client"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, _ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let session = Session::current().ok_or("No session")?;
+        let client = session.client_info().ok_or("Unknown client")?;
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: format!("{} ({})", client.name, session.id()),
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_session_available_to_tools() {
+    let server = McpServer::new("test-server", "1.0.0");
+    server.register_tool(WhoAmITool).await;
+    let (session, _) = initialize_session(&server, "session-1", LATEST_PROTOCOL_VERSION).await;
+
+    let request = || method_call("tools/call", json!({ "name": "whoami" }));
+    match server
+        .handle_session_request(&session, request())
+        .await
+        .unwrap()
+        .unwrap()
+    {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(
+                success.result["content"][0]["text"],
+                "test-client (session-1)"
+            );
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    // Outside a session the tool has nobody to report
+    match server.handle_request(request()).await.unwrap().unwrap() {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(success.result["isError"], true);
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+/// Asks the client to confirm before answering
+struct ConfirmTool;
+
+#[async_trait]
+impl McpTool for ConfirmTool {
+    fn name(&self) -> &str {
+        "confirm"
+    }
+
+    fn description(&self) -> &str {
+        "Asks the client for confirmation"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let client = ctx.client_info().ok_or("Unknown client")?;
+        let answer = ctx
+            .send_request("test/confirm", json!({ "client": client.name }))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: format!("{:?}: {}", ctx.request_id(), answer["confirmed"]),
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_request_context() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(ConfirmTool).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("context".to_string(), tx);
+    server
+        .handle_session_request(
+            &session,
+            method_call(
+                "initialize",
+                json!({ "clientInfo": { "name": "test-client", "version": "1.0.0" } }),
+            ),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    let call = tokio::spawn({
+        let server = server.clone();
+        let session = session.clone();
+        async move {
+            server
+                .handle_session_request(
+                    &session,
+                    method_call("tools/call", json!({ "name": "confirm" })),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+        }
+    });
+
+    // Answer the tool's request as the client would
+    let request = match rx.recv().await.unwrap() {
+        Call::MethodCall(request) => request,
+        _ => panic!("Expected request"),
+    };
+    assert_eq!(request.method, "test/confirm");
+    assert_eq!(
+        request.params,
+        Params::Map(
+            json!({ "client": "test-client" })
+                .as_object()
+                .unwrap()
+                .clone()
+        )
+    );
+    server.handle_session_response(
+        &session,
+        Output::Success(jsonrpc_core::Success {
+            jsonrpc: Some(Version::V2),
+            result: json!({ "confirmed": true }),
+            id: request.id,
+        }),
+    );
+
+    match call.await.unwrap() {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(success.result["content"][0]["text"], "Num(1): true");
+        }
+        _ => panic!("Expected successful response"),
+    }
+
+    // Outside a session there is nobody to ask
+    match server
+        .handle_request(method_call("tools/call", json!({ "name": "confirm" })))
+        .await
+        .unwrap()
+        .unwrap()
+    {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            assert_eq!(success.result["isError"], true);
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+/// Reports progress as fast as it can
+struct IndexTool;
+
+#[async_trait]
+impl McpTool for IndexTool {
+    fn name(&self) -> &str {
+        "index"
+    }
+
+    fn description(&self) -> &str {
+        "Indexes ten files"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let sent = (1..=10)
+            .filter(|&file| {
+                ctx.report_progress(file as f64, Some(10.0), Some(&format!("file {}", file)))
+            })
+            .count();
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: sent.to_string(),
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_progress_notifications() {
+    let server =
+        McpServer::new("test-server", "1.0.0").with_progress_interval(Duration::from_secs(60));
+    server.register_tool(IndexTool).await;
+
+    let progress = |rx: &mut tokio::sync::mpsc::UnboundedReceiver<Call>| {
+        let mut notifications = Vec::new();
+        while let Ok(Call::Notification(notification)) = rx.try_recv() {
+            assert_eq!(notification.method, "notifications/progress");
+            notifications.push(serde_json::to_value(notification.params).unwrap());
+        }
+        notifications
+    };
+
+    for (version, message) in [
+        (LATEST_PROTOCOL_VERSION, Some("file 10")),
+        (PROTOCOL_VERSION_2024_11_05, None),
+    ] {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let session = server.connect(version.to_string(), tx);
+        server
+            .handle_session_request(
+                &session,
+                method_call("initialize", json!({ "protocolVersion": version })),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        let response = server
+            .handle_session_request(
+                &session,
+                method_call(
+                    "tools/call",
+                    json!({ "name": "index", "_meta": { "progressToken": "index-1" } }),
+                ),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        match response {
+            JsonRpcResponse::Single(Output::Success(success)) => {
+                // The first update and the final one; the rest fall inside the interval
+                assert_eq!(success.result["content"][0]["text"], "2");
+            }
+            _ => panic!("Expected successful response"),
+        }
+
+        let notifications = progress(&mut rx);
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0]["progressToken"], "index-1");
+        assert_eq!(notifications[0]["progress"], 1.0);
+        assert_eq!(notifications[1]["progress"], 10.0);
+        assert_eq!(notifications[1]["total"], 10.0);
+        assert_eq!(
+            notifications[1].get("message").and_then(Value::as_str),
+            message
+        );
+
+        // Without a token the client gets no progress
+        server
+            .handle_session_request(
+                &session,
+                method_call("tools/call", json!({ "name": "index" })),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(progress(&mut rx).is_empty());
+    }
+}
+
+/// Never finishes on its own; flags when its cancellation token fires
+struct StuckTool {
+    cancelled: Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[async_trait]
+impl McpTool for StuckTool {
+    fn name(&self) -> &str {
+        "stuck"
+    }
+
+    fn description(&self) -> &str {
+        "Waits forever"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let token = ctx.cancellation_token().clone();
+        let cancelled = self.cancelled.clone();
+        tokio::spawn(async move {
+            token.cancelled().await;
+            cancelled.store(true, std::sync::atomic::Ordering::SeqCst);
+        });
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_request_cancellation() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    let cancelled = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    server
+        .register_tool(StuckTool {
+            cancelled: cancelled.clone(),
+        })
+        .await;
+    let (session, _) = initialize_session(&server, "cancel", LATEST_PROTOCOL_VERSION).await;
+
+    let call = tokio::spawn({
+        let server = server.clone();
+        let session = session.clone();
+        async move {
+            server
+                .handle_session_request(
+                    &session,
+                    method_call("tools/call", json!({ "name": "stuck" })),
+                )
+                .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!call.is_finished());
+
+    // An id may not be reused while its request is in flight, or cancelling it would be
+    // ambiguous
+    let duplicate = server
+        .handle_session_request(&session, method_call("ping", json!({})))
+        .await;
+    assert!(matches!(duplicate, Err(McpError::InvalidRequest { .. })));
+
+    server
+        .handle_session_request(
+            &session,
+            JsonRpcRequest::Single(Call::Notification(Notification {
+                jsonrpc: Some(Version::V2),
+                method: "notifications/cancelled".to_string(),
+                params: Params::Map(
+                    json!({ "requestId": 1, "reason": "User pressed stop" })
+                        .as_object()
+                        .unwrap()
+                        .clone(),
+                ),
+            })),
+        )
+        .await
+        .unwrap();
+
+    let result = tokio::time::timeout(Duration::from_secs(1), call)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(result, Err(McpError::RequestCancelled)));
+    tokio::task::yield_now().await;
+    assert!(cancelled.load(std::sync::atomic::Ordering::SeqCst));
+
+    // Once the request has finished its id is free again
+    let reused = server
+        .handle_session_request(&session, method_call("ping", json!({})))
+        .await;
+    assert!(matches!(
+        reused,
+        Ok(Some(JsonRpcResponse::Single(Output::Success(_))))
+    ));
+}
+
+/// Completes only once `parties` calls are running at the same time
+struct RendezvousTool {
+    barrier: Arc<tokio::sync::Barrier>,
+}
+
+#[async_trait]
+impl McpTool for RendezvousTool {
+    fn name(&self) -> &str {
+        "rendezvous"
+    }
+
+    fn description(&self) -> &str {
+        "Waits for other callers"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, _ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        self.barrier.wait().await;
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: "met".to_string(),
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_tool_calls_run_in_parallel() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server
+        .register_tool(RendezvousTool {
+            barrier: Arc::new(tokio::sync::Barrier::new(3)),
+        })
+        .await;
+
+    // Would deadlock if the registry were locked for the duration of a call
+    let calls = (0..3)
+        .map(|_| server.handle_request(method_call("tools/call", json!({ "name": "rendezvous" }))));
+    let responses = tokio::time::timeout(Duration::from_secs(5), futures::future::join_all(calls))
+        .await
+        .expect("tool calls were serialized");
+    for response in responses {
+        assert!(matches!(
+            response.unwrap().unwrap(),
+            JsonRpcResponse::Single(Output::Success(_))
+        ));
+    }
+}
+
+/// Names returned by `tools/list` on `session`
+async fn listed_tools(server: &McpServer, session: &Arc<Session>) -> Vec<String> {
+    let response = server
+        .handle_session_request(session, method_call("tools/list", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Single(Output::Success(success)) => {
+            let result: ListToolsResult = serde_json::from_value(success.result).unwrap();
+            result.tools.into_iter().map(|tool| tool.name).collect()
+        }
+        _ => panic!("Expected successful response"),
+    }
+}
+
+#[tokio::test]
+async fn test_dynamic_tool_registration() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(MockTool).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("dynamic".to_string(), tx);
+    server
+        .handle_session_request(&session, method_call("initialize", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+    let mut list_changed = move || {
+        let mut count = 0;
+        while let Ok(call) = rx.try_recv() {
+            match call {
+                Call::Notification(notification) => {
+                    assert_eq!(notification.method, "notifications/tools/list_changed");
+                    count += 1;
+                }
+                _ => panic!("Expected notification"),
+            }
+        }
+        count
+    };
+
+    // Sessions that have not finished initializing are not told about changes
+    server.register_tool(WeatherTool).await;
+    assert_eq!(list_changed(), 0);
+
+    server
+        .handle_session_request(
+            &session,
+            JsonRpcRequest::Single(Call::Notification(Notification {
+                jsonrpc: Some(Version::V2),
+                method: "notifications/initialized".to_string(),
+                params: Params::None,
+            })),
+        )
+        .await
+        .unwrap();
+
+    assert!(server.disable_tool("weather").await);
+    assert!(!server.is_tool_enabled("weather").await);
+    assert_eq!(list_changed(), 1);
+    assert_eq!(listed_tools(&server, &session).await, vec!["mock_tool"]);
+    let response = server
+        .handle_session_request(
+            &session,
+            method_call("tools/call", json!({ "name": "weather" })),
+        )
+        .await;
+    assert!(matches!(response, Err(McpError::InvalidParams { .. })));
+
+    // Toggling to the current state changes nothing
+    assert!(server.disable_tool("weather").await);
+    assert_eq!(list_changed(), 0);
+    assert!(server.enable_tool("weather").await);
+    assert_eq!(list_changed(), 1);
+    let mut names = listed_tools(&server, &session).await;
+    names.sort();
+    assert_eq!(names, vec!["mock_tool", "weather"]);
+
+    assert!(server.replace_tool(MockTool).await);
+    assert_eq!(list_changed(), 1);
+    assert!(!server.replace_tool(WhoAmITool).await);
+    assert!(!server.is_tool_enabled("whoami").await);
+
+    assert!(server.unregister_tool("weather").await);
+    assert!(!server.unregister_tool("weather").await);
+    assert!(!server.enable_tool("weather").await);
+    assert_eq!(list_changed(), 1);
+    assert_eq!(listed_tools(&server, &session).await, vec!["mock_tool"]);
+}
+
+/// Synthetic `initialize` params covering the capability shapes clients send in practice:
+/// nothing at all, a subset, every spec capability, non-standard fields and explicit nulls
+fn sample_initialize_params() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "no-capabilities",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": { "name": "minimal-client", "version": "0.1.0" }
+            }),
+        ),
+        (
+            "some-capabilities",
+            json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {
+                    "sampling": {},
+                    "roots": { "listChanged": true }
+                },
+                "clientInfo": { "name": "partial-client", "version": "1.0.0" }
+            }),
+        ),
+        (
+            "all-capabilities",
+            json!({
+                "protocolVersion": "2025-06-18",
+                "capabilities": {
+                    "roots": { "listChanged": true },
+                    "sampling": {},
+                    "elicitation": {}
+                },
+                "clientInfo": { "name": "complete-client", "version": "1.0.0" }
+            }),
+        ),
+        (
+            "non-standard-capabilities",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {
+                    "tools": true,
+                    "prompts": false,
+                    "resources": true,
+                    "logging": false,
+                    "roots": { "listChanged": false }
+                },
+                "clientInfo": { "name": "lenient-client", "version": "1.0.0" }
+            }),
+        ),
+        (
+            "null-capabilities",
+            json!({
+                "protocolVersion": "2025-06-18",
+                "capabilities": {
+                    "experimental": null,
+                    "sampling": null,
+                    "elicitation": null,
+                    "roots": null
+                },
+                "clientInfo": { "name": "null-client", "version": "0.1.0" }
+            }),
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_client_capabilities_shapes() {
+    let server = McpServer::new("test-server", "1.0.0");
+
+    for (client, params) in sample_initialize_params() {
+        let parsed: InitializeParams =
+            serde_json::from_value(params.clone()).unwrap_or_else(|e| panic!("{client}: {e}"));
+        assert_eq!(
+            parsed.protocol_version, params["protocolVersion"],
+            "{client}"
+        );
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let session = server.connect(client.to_string(), tx);
+        server
+            .handle_session_request(&session, method_call("initialize", params))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(session.client_capabilities().is_some(), "{client}");
+    }
+
+    let capabilities = |client: &str| {
+        server
+            .session(client)
+            .and_then(|session| session.client_capabilities())
+            .unwrap()
+    };
+    let all = capabilities("all-capabilities");
+    assert!(all.elicitation.is_some());
+    assert!(all.sampling.is_some());
+    assert_eq!(all.roots.unwrap().list_changed, Some(true));
+
+    let none = capabilities("no-capabilities");
+    assert!(none.roots.is_none() && none.sampling.is_none());
+
+    let nulls = capabilities("null-capabilities");
+    assert!(nulls.roots.is_none() && nulls.elicitation.is_none());
+
+    // Non-standard fields survive a round trip
+    let non_standard = capabilities("non-standard-capabilities");
+    assert_eq!(non_standard.other.get("tools"), Some(&json!(true)));
+    assert_eq!(
+        serde_json::to_value(&non_standard).unwrap(),
+        json!({
+            "tools": true,
+            "prompts": false,
+            "resources": true,
+            "logging": false,
+            "roots": { "listChanged": false }
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_batch_requests() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    // Both calls must be running at once for either to finish
+    server
+        .register_tool(RendezvousTool {
+            barrier: Arc::new(tokio::sync::Barrier::new(2)),
+        })
+        .await;
+    let call = |id: u64, method: &str, params: Value| {
+        Call::MethodCall(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params: Params::Map(params.as_object().unwrap().clone()),
+            id: Id::Num(id),
+        })
+    };
+
+    let batch = JsonRpcRequest::Batch(vec![
+        call(1, "tools/call", json!({ "name": "rendezvous" })),
+        Call::Notification(Notification {
+            jsonrpc: Some(Version::V2),
+            method: "notifications/initialized".to_string(),
+            params: Params::None,
+        }),
+        call(2, "tools/call", json!({ "name": "missing" })),
+        call(3, "tools/call", json!({ "name": "rendezvous" })),
+    ]);
+    let response = tokio::time::timeout(Duration::from_secs(5), server.handle_request(batch))
+        .await
+        .expect("batch calls were serialized")
+        .unwrap();
+    let Some(JsonRpcResponse::Batch(outputs)) = response else {
+        panic!("Expected batch response");
+    };
+    let mut ids: Vec<_> = outputs
+        .iter()
+        .map(|output| match output {
+            Output::Success(success) => (success.id.clone(), true),
+            Output::Failure(failure) => (failure.id.clone(), false),
+        })
+        .collect();
+    ids.sort_by_key(|(id, _)| format!("{:?}", id));
+    assert_eq!(
+        ids,
+        vec![(Id::Num(1), true), (Id::Num(2), false), (Id::Num(3), true)]
+    );
+
+    // A batch of notifications has nothing to answer
+    let response = server
+        .handle_request(JsonRpcRequest::Batch(vec![Call::Notification(
+            Notification {
+                jsonrpc: Some(Version::V2),
+                method: "notifications/initialized".to_string(),
+                params: Params::None,
+            },
+        )]))
+        .await
+        .unwrap();
+    assert!(response.is_none());
+
+    // An empty batch is itself invalid, and initialize must be sent alone
+    let response = server
+        .handle_request(JsonRpcRequest::Batch(vec![]))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        response,
+        JsonRpcResponse::Single(Output::Failure(failure)) if failure.id == Id::Null
+    ));
+    let response = server
+        .handle_request(JsonRpcRequest::Batch(vec![call(
+            4,
+            "initialize",
+            json!({}),
+        )]))
+        .await
+        .unwrap()
+        .unwrap();
+    match response {
+        JsonRpcResponse::Batch(outputs) => {
+            assert!(matches!(&outputs[..], [Output::Failure(failure)]
+                if failure.error.code == jsonrpc_core::ErrorCode::InvalidRequest));
+        }
+        _ => panic!("Expected batch response"),
+    }
+}
+
+#[tokio::test]
+async fn test_batches_follow_protocol_version() {
+    let server = McpServer::new("test-server", "1.0.0");
+    let batch = || {
+        JsonRpcRequest::Batch(vec![
+            Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: "ping".to_string(),
+                params: Params::None,
+                id: Id::Num(1),
+            }),
+            Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: "ping".to_string(),
+                params: Params::None,
+                id: Id::Num(2),
+            }),
+        ])
+    };
+
+    let (session, _) = initialize_session(&server, "batching", PROTOCOL_VERSION_2025_03_26).await;
+    let response = server
+        .handle_session_request(&session, batch())
+        .await
+        .unwrap();
+    match response {
+        Some(JsonRpcResponse::Batch(outputs)) => {
+            assert_eq!(outputs.len(), 2);
+            assert!(outputs
+                .iter()
+                .all(|output| matches!(output, Output::Success(_))));
+        }
+        _ => panic!("Expected batch response"),
+    }
+
+    // 2025-06-18 removed batching
+    let (session, _) =
+        initialize_session(&server, "no-batching", PROTOCOL_VERSION_2025_06_18).await;
+    let response = server
+        .handle_session_request(&session, batch())
+        .await
+        .unwrap();
+    match response {
+        Some(JsonRpcResponse::Single(Output::Failure(failure))) => {
+            assert_eq!(failure.id, Id::Null);
+            assert_eq!(failure.error.code, jsonrpc_core::ErrorCode::InvalidRequest);
+        }
+        _ => panic!("Expected failure response"),
+    }
+}
+
+#[tokio::test]
+async fn test_notification_handlers() {
+    let server = McpServer::new("test-server", "1.0.0");
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("notifications".to_string(), tx);
+    let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+    for method in ["notifications/roots/list_changed", "notifications/custom"] {
+        let received = received.clone();
+        server.on_notification(method, move |session, params| {
+            let session_id = session.map(|session| session.id().to_string());
+            received.lock().unwrap().push((session_id, params));
+        });
+    }
+    let notification = |method: &str, params: Params| {
+        JsonRpcRequest::Single(Call::Notification(Notification {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params,
+        }))
+    };
+
+    let response = server
+        .handle_session_request(
+            &session,
+            notification("notifications/roots/list_changed", Params::None),
+        )
+        .await
+        .unwrap();
+    assert!(response.is_none());
+    let response = server
+        .handle_request(notification(
+            "notifications/custom",
+            Params::Map(json!({ "value": 1 }).as_object().unwrap().clone()),
+        ))
+        .await
+        .unwrap();
+    assert!(response.is_none());
+    // Notifications nobody handles are dropped quietly
+    let response = server
+        .handle_session_request(
+            &session,
+            notification("notifications/unknown", Params::None),
+        )
+        .await
+        .unwrap();
+    assert!(response.is_none());
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            (Some("notifications".to_string()), Value::Null),
+            (None, json!({ "value": 1 })),
+        ]
+    );
+}
+
+/// Lists the client's roots
+struct RootsTool;
+
+#[async_trait]
+impl McpTool for RootsTool {
+    fn name(&self) -> &str {
+        "roots"
+    }
+
+    fn description(&self) -> &str {
+        "Lists the directories the client shares"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let roots = ctx.roots().await?;
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: roots
+                    .into_iter()
+                    .map(|root| root.uri)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+/// Answers the next `roots/list` request sent on `session` as the client would.
+async fn answer_roots(
+    server: &McpServer,
+    session: &Session,
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<Call>,
+    roots: Value,
+) {
+    let request = match rx.recv().await.unwrap() {
+        Call::MethodCall(request) => request,
+        _ => panic!("Expected request"),
+    };
+    assert_eq!(request.method, "roots/list");
+    server.handle_session_response(
+        session,
+        Output::Success(jsonrpc_core::Success {
+            jsonrpc: Some(Version::V2),
+            result: json!({ "roots": roots }),
+            id: request.id,
+        }),
+    );
+}
+
+#[tokio::test]
+async fn test_roots() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(RootsTool).await;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("roots".to_string(), tx);
+    server
+        .handle_session_request(
+            &session,
+            method_call(
+                "initialize",
+                json!({ "capabilities": { "roots": { "listChanged": true } } }),
+            ),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    let call_tool = || {
+        let server = server.clone();
+        let session = session.clone();
+        tokio::spawn(async move {
+            match server
+                .handle_session_request(
+                    &session,
+                    method_call("tools/call", json!({ "name": "roots" })),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+            {
+                JsonRpcResponse::Single(Output::Success(success)) => success.result,
+                _ => panic!("Expected successful response"),
+            }
+        })
+    };
+    let call = call_tool();
+    answer_roots(
+        &server,
+        &session,
+        &mut rx,
+        json!([
+            { "uri": "file:///home/user/project", "name": "project" },
+            { "uri": "file:///home/user/notes" }
+        ]),
+    )
+    .await;
+    assert_eq!(
+        call.await.unwrap()["content"][0]["text"],
+        "file:///home/user/project,file:///home/user/notes"
+    );
+
+    // The answer is reused until the client says its roots changed
+    let result = tokio::time::timeout(Duration::from_secs(1), call_tool())
+        .await
+        .expect("roots were not cached")
+        .unwrap();
+    assert_eq!(
+        result["content"][0]["text"],
+        "file:///home/user/project,file:///home/user/notes"
+    );
+    let response = server
+        .handle_session_request(
+            &session,
+            JsonRpcRequest::Single(Call::Notification(Notification {
+                jsonrpc: Some(Version::V2),
+                method: "notifications/roots/list_changed".to_string(),
+                params: Params::None,
+            })),
+        )
+        .await
+        .unwrap();
+    assert!(response.is_none());
+    let call = call_tool();
+    answer_roots(
+        &server,
+        &session,
+        &mut rx,
+        json!([{ "uri": "file:///tmp" }]),
+    )
+    .await;
+    assert_eq!(call.await.unwrap()["content"][0]["text"], "file:///tmp");
+
+    // Clients without the capability are never asked
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let rootless = server.connect("rootless".to_string(), tx);
+    server
+        .handle_session_request(&rootless, method_call("initialize", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(rootless.roots().await, Err(PeerError::Unsupported("roots")));
+    assert!(rx.try_recv().is_err());
+}
+
+/// Has the client's model summarize its input
+struct SummarizeTool;
+
+#[async_trait]
+impl McpTool for SummarizeTool {
+    fn name(&self) -> &str {
+        "summarize"
+    }
+
+    fn description(&self) -> &str {
+        "Summarizes a text"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": { "text": { "type": "string" } } })
+    }
+
+    async fn execute(&self, args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let text = args["text"].as_str().unwrap_or_default();
+        let request = CreateMessageRequest::new(vec![SamplingMessage::user(text)], 100)
+            .with_system_prompt("Summarize in one word")
+            .with_model_preferences(ModelPreferences {
+                hints: vec![ModelHint {
+                    name: Some("sonnet".to_string()),
+                }],
+                speed_priority: Some(1.0),
+                ..Default::default()
+            });
+        let result = ctx.create_message(request).await?;
+        let Content::Text { text } = result.content else {
+            return Err("Expected text".into());
+        };
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text: format!("{} ({})", text, result.model),
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_sampling() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(SummarizeTool).await;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("sampling".to_string(), tx);
+    server
+        .handle_session_request(
+            &session,
+            method_call("initialize", json!({ "capabilities": { "sampling": {} } })),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    let call_tool = |session: Arc<Session>| {
+        let server = server.clone();
+        tokio::spawn(async move {
+            match server
+                .handle_session_request(
+                    &session,
+                    method_call(
+                        "tools/call",
+                        json!({ "name": "summarize", "arguments": { "text": "A long story" } }),
+                    ),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+            {
+                JsonRpcResponse::Single(Output::Success(success)) => success.result,
+                _ => panic!("Expected successful response"),
+            }
+        })
+    };
+
+    let call = call_tool(session.clone());
+    let request = match rx.recv().await.unwrap() {
+        Call::MethodCall(request) => request,
+        _ => panic!("Expected request"),
+    };
+    assert_eq!(request.method, "sampling/createMessage");
+    assert_eq!(
+        request.params,
+        Params::Map(
+            json!({
+                "messages": [
+                    { "role": "user", "content": { "type": "text", "text": "A long story" } }
+                ],
+                "modelPreferences": { "hints": [{ "name": "sonnet" }], "speedPriority": 1.0 },
+                "systemPrompt": "Summarize in one word",
+                "maxTokens": 100
+            })
+            .as_object()
+            .unwrap()
+            .clone()
+        )
+    );
+    server.handle_session_response(
+        &session,
+        Output::Success(jsonrpc_core::Success {
+            jsonrpc: Some(Version::V2),
+            result: json!({
+                "role": "assistant",
+                "content": { "type": "text", "text": "Story" },
+                "model": "claude-sonnet",
+                "stopReason": "endTurn"
+            }),
+            id: request.id,
+        }),
+    );
+    let result = call.await.unwrap();
+    assert_eq!(result["content"][0]["text"], "Story (claude-sonnet)");
+
+    // Clients that can't sample get a tool error instead of a request
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("no-sampling".to_string(), tx);
+    server
+        .handle_session_request(&session, method_call("initialize", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+    let result = call_tool(session).await.unwrap();
+    assert_eq!(result["isError"], true);
+    assert_eq!(
+        result["content"][0]["text"],
+        "Client does not support sampling"
+    );
+    assert!(rx.try_recv().is_err());
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct DeleteConfirmation {
+    /// Whether to really delete everything
+    confirm: bool,
+    reason: Option<String>,
+    /// What to delete
+    scope: Option<DeleteScope>,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum DeleteScope {
+    Files,
+    Everything,
+}
+
+#[derive(schemars::JsonSchema)]
+#[allow(dead_code)]
+struct Deployment {
+    /// Where to deploy
+    environment: Environment,
+}
+
+#[derive(schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[allow(dead_code)]
+enum Environment {
+    /// Internal testing
+    Staging,
+    /// Live traffic
+    Production,
+}
+
+#[derive(schemars::JsonSchema)]
+#[allow(dead_code)]
+struct MoveTarget {
+    destination: MoveDestination,
+}
+
+#[derive(schemars::JsonSchema)]
+#[allow(dead_code)]
+struct MoveDestination {
+    path: String,
+}
+
+/// Asks the user before deleting anything
+struct DeleteTool;
+
+#[async_trait]
+impl McpTool for DeleteTool {
+    fn name(&self) -> &str {
+        "delete"
+    }
+
+    fn description(&self) -> &str {
+        "Deletes everything"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        let text = match ctx
+            .elicit::<DeleteConfirmation>("Delete everything?")
+            .await?
+        {
+            Elicitation::Accept(answer) if answer.confirm => match answer.scope {
+                Some(DeleteScope::Files) => "Deleted files".to_string(),
+                Some(DeleteScope::Everything) | None => {
+                    format!("Deleted: {}", answer.reason.unwrap_or_default())
+                }
+            },
+            Elicitation::Accept(_) => "Kept".to_string(),
+            Elicitation::Decline => "Declined".to_string(),
+            Elicitation::Cancel => "Cancelled".to_string(),
+        };
+        Ok(ToolResult {
+            content: vec![ToolContent {
+                r#type: "text".to_string(),
+                text,
+            }],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_elicitation() {
+    let server = Arc::new(McpServer::new("test-server", "1.0.0"));
+    server.register_tool(DeleteTool).await;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("elicitation".to_string(), tx);
+    server
+        .handle_session_request(
+            &session,
+            method_call(
+                "initialize",
+                json!({ "capabilities": { "elicitation": {} } }),
+            ),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    let call_tool = |session: Arc<Session>| {
+        let server = server.clone();
+        tokio::spawn(async move {
+            match server
+                .handle_session_request(
+                    &session,
+                    method_call("tools/call", json!({ "name": "delete" })),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+            {
+                JsonRpcResponse::Single(Output::Success(success)) => success.result,
+                _ => panic!("Expected successful response"),
+            }
+        })
+    };
+
+    for (answer, expected) in [
+        (
+            json!({ "action": "accept", "content": { "confirm": true, "reason": "cleanup" } }),
+            "Deleted: cleanup",
+        ),
+        (
+            json!({ "action": "accept", "content": { "confirm": true, "scope": "files" } }),
+            "Deleted files",
+        ),
+        (
+            json!({ "action": "accept", "content": { "confirm": false } }),
+            "Kept",
+        ),
+        (json!({ "action": "decline" }), "Declined"),
+        (json!({ "action": "cancel" }), "Cancelled"),
+    ] {
+        let call = call_tool(session.clone());
+        let request = match rx.recv().await.unwrap() {
+            Call::MethodCall(request) => request,
+            _ => panic!("Expected request"),
+        };
+        assert_eq!(request.method, "elicitation/create");
+        let params: ElicitRequest = request.params.parse().unwrap();
+        assert_eq!(params.message, "Delete everything?");
+        let schema = params.requested_schema;
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["confirm"]["type"], "boolean");
+        assert_eq!(
+            schema["properties"]["confirm"]["description"],
+            "Whether to really delete everything"
+        );
+        assert_eq!(schema["required"], json!(["confirm"]));
+        assert_eq!(schema["properties"]["reason"], json!({ "type": "string" }));
+        assert_eq!(
+            schema["properties"]["scope"],
+            json!({
+                "description": "What to delete",
+                "type": "string",
+                "enum": ["files", "everything"]
+            })
+        );
+        assert!(schema.get("definitions").is_none());
+        server.handle_session_response(
+            &session,
+            Output::Success(jsonrpc_core::Success {
+                jsonrpc: Some(Version::V2),
+                result: answer,
+                id: request.id,
+            }),
+        );
+        assert_eq!(call.await.unwrap()["content"][0]["text"], expected);
+    }
+
+    // Clients that can't ask their user get a tool error instead of a request
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("no-elicitation".to_string(), tx);
+    server
+        .handle_session_request(&session, method_call("initialize", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+    let result = call_tool(session).await.unwrap();
+    assert_eq!(result["isError"], true);
+    assert_eq!(
+        result["content"][0]["text"],
+        "Client does not support elicitation"
+    );
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn test_elicitation_schema_must_be_flat() {
+    assert_eq!(
+        ElicitRequest::new::<MoveTarget>("Move where?").unwrap_err(),
+        "Property destination of MoveTarget is not a string, number, boolean or enum"
+    );
+    assert_eq!(
+        ElicitRequest::new::<Vec<String>>("Which files?").unwrap_err(),
+        "Array_of_String is not an object"
+    );
+
+    // Documented variants make schemars generate `oneOf`, which is flattened into an enum
+    let request = ElicitRequest::new::<Deployment>("Deploy where?").unwrap();
+    assert_eq!(
+        request.requested_schema["properties"]["environment"],
+        json!({
+            "description": "Where to deploy",
+            "type": "string",
+            "enum": ["staging", "production"]
+        })
+    );
+}
+
+/// Logs through `tracing` while it runs
+struct ChattyTool;
+
+#[async_trait]
+impl McpTool for ChattyTool {
+    fn name(&self) -> &str {
+        "chatty"
+    }
+
+    fn description(&self) -> &str {
+        "Logs what it does"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _args: Value, _ctx: RequestContext) -> Result<ToolResult, ToolError> {
+        tracing::info!(target: "chatty", items = 3, "Processing");
+        tracing::debug!(target: "chatty", "Processed item");
+        Ok(ToolResult {
+            content: vec![],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_logging() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let server = McpServer::new("test-server", "1.0.0");
+    server.register_tool(ChattyTool).await;
+    let _subscriber = tracing::subscriber::set_default(
+        tracing_subscriber::registry().with(server.logging_layer()),
+    );
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let session = server.connect("logging".to_string(), tx);
+    server
+        .handle_session_request(&session, method_call("initialize", json!({})))
+        .await
+        .unwrap()
+        .unwrap();
+    let mut logged = || {
+        let mut messages = Vec::new();
+        while let Ok(call) = rx.try_recv() {
+            match call {
+                Call::Notification(notification)
+                    if notification.method == "notifications/message" =>
+                {
+                    messages.push(notification.params.parse::<Value>().unwrap());
+                }
+                _ => panic!("Expected log message"),
+            }
+        }
+        messages
+    };
+    let call_tool = || {
+        server.handle_session_request(
+            &session,
+            method_call("tools/call", json!({ "name": "chatty" })),
+        )
+    };
+
+    // Info and above until the client picks a level; the server's own events stay local
+    call_tool().await.unwrap().unwrap();
+    assert_eq!(
+        logged(),
+        vec![json!({
+            "level": "info",
+            "logger": "chatty",
+            "data": { "message": "Processing", "items": 3 }
+        })]
+    );
+
+    let set_level = |level: &str| {
+        server.handle_session_request(
+            &session,
+            method_call("logging/setLevel", json!({ "level": level })),
+        )
+    };
+    match set_level("debug").await.unwrap().unwrap() {
+        JsonRpcResponse::Single(Output::Success(success)) => assert_eq!(success.result, json!({})),
+        _ => panic!("Expected successful response"),
+    }
+    call_tool().await.unwrap().unwrap();
+    assert_eq!(
+        logged()
+            .iter()
+            .map(|message| message["level"].clone())
+            .collect::<Vec<_>>(),
+        vec![json!("info"), json!("debug")]
+    );
+    assert_eq!(session.log_level(), LoggingLevel::Debug);
+
+    set_level("error").await.unwrap().unwrap();
+    call_tool().await.unwrap().unwrap();
+    assert!(logged().is_empty());
+
+    // Work outside a request reaches the session named by its span
+    let span = tracing::info_span!("job", session_id = %session.id());
+    span.in_scope(|| tracing::error!(target: "jobs", "Job failed"));
+    tracing::error!(target: "jobs", "Unrelated failure");
+    assert_eq!(
+        logged(),
+        vec![json!({ "level": "error", "logger": "jobs", "data": "Job failed" })]
+    );
+
+    // The level belongs to a session
+    match server
+        .handle_request(method_call("logging/setLevel", json!({ "level": "debug" })))
+        .await
+        .unwrap()
+        .unwrap()
+    {
+        JsonRpcResponse::Single(Output::Failure(failure)) => {
+            assert_eq!(failure.error.code, jsonrpc_core::ErrorCode::InvalidRequest);
+        }
+        _ => panic!("Expected failure response"),
+    }
 }
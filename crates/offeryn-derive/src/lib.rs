@@ -24,19 +24,16 @@ fn extract_doc_string(attrs: &[Attribute]) -> String {
 }
 
 fn get_type_schema(ty: &Type) -> proc_macro2::TokenStream {
-    match ty {
-        Type::Path(type_path) => {
-            if let Some(segment) = type_path.path.segments.last() {
-                if segment.ident == "Result" {
-                    if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                        if let Some(syn::GenericArgument::Type(ok_type)) = args.args.first() {
-                            return get_type_schema(ok_type);
-                        }
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if segment.ident == "Result" {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(syn::GenericArgument::Type(ok_type)) = args.args.first() {
+                        return get_type_schema(ok_type);
                     }
                 }
             }
         }
-        _ => {}
     }
     quote! {
         <#ty as schemars::JsonSchema>::json_schema(&mut schemars::gen::SchemaGenerator::default())
@@ -45,7 +42,29 @@ fn get_type_schema(ty: &Type) -> proc_macro2::TokenStream {
 
 fn is_optional_type(ty: &Type) -> bool {
     matches!(ty, Type::Path(type_path) if type_path.path.segments.last()
-        .map_or(false, |segment| segment.ident == "Option"))
+        .is_some_and(|segment| segment.ident == "Option"))
+}
+
+/// Whether a method returns `Result<_, ToolError>`, whose errors keep their kind instead of
+/// all becoming execution errors.
+fn returns_tool_error(output: &ReturnType) -> bool {
+    let ReturnType::Type(_, ty) = output else {
+        return false;
+    };
+    let Type::Path(type_path) = ty.as_ref() else {
+        return false;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return false;
+    };
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return false;
+    };
+    matches!(
+        args.args.iter().nth(1),
+        Some(syn::GenericArgument::Type(Type::Path(error)))
+            if error.path.segments.last().is_some_and(|s| s.ident == "ToolError")
+    )
 }
 
 fn extract_param_doc(docs: &str, param_name: &str) -> String {
@@ -110,7 +129,9 @@ fn generate_param_deserialization(param_name: &str, is_optional: bool) -> proc_m
     if is_optional {
         quote! {
             match args.get(#name_str) {
-                Some(v) => Some(serde_json::from_value(v.clone()).map_err(|e| e.to_string())?),
+                Some(v) => Some(serde_json::from_value(v.clone()).map_err(|e| {
+                    offeryn_types::ToolError::invalid_arguments(format!("{}: {}", #name_str, e))
+                })?),
                 None => None
             }
         }
@@ -118,19 +139,49 @@ fn generate_param_deserialization(param_name: &str, is_optional: bool) -> proc_m
         quote! {
             serde_json::from_value(
                 args.get(#name_str)
-                    .ok_or_else(|| format!("Missing required parameter: {}", #name_str))?
+                    .ok_or_else(|| offeryn_types::ToolError::invalid_arguments(
+                        format!("Missing required parameter: {}", #name_str)
+                    ))?
                     .clone()
-            ).map_err(|e| e.to_string())?
+            ).map_err(|e| {
+                offeryn_types::ToolError::invalid_arguments(format!("{}: {}", #name_str, e))
+            })?
         }
     }
 }
 
-#[proc_macro_attribute]
-pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(item as ItemImpl);
-    let ty = &*input.self_ty;
+/// Whether a tool parameter asks for the `RequestContext`, by value or by reference.
+fn is_request_context(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) => is_request_context(&reference.elem),
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "RequestContext"),
+        _ => false,
+    }
+}
 
-    let type_name = if let Type::Path(type_path) = ty {
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(syn::GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn impl_type_name(ty: &Type) -> String {
+    if let Type::Path(type_path) = ty {
         type_path
             .path
             .segments
@@ -139,7 +190,14 @@ pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
             .expect("Expected a type with at least one segment")
     } else {
         panic!("Expected a path type")
-    };
+    }
+}
+
+#[proc_macro_attribute]
+pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemImpl);
+    let ty = &*input.self_ty;
+    let type_name = impl_type_name(ty);
 
     let mut tool_impls = Vec::new();
     let mut tool_names = Vec::new();
@@ -159,12 +217,22 @@ pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
             let mut param_schemas = Vec::new();
             let mut param_desers = Vec::new();
+            let mut uses_context = false;
 
             for param in &method.sig.inputs {
                 if let FnArg::Typed(pat_type) = param {
                     if let Pat::Ident(param_name) = &*pat_type.pat {
                         if param_name.ident != "self" {
                             let param_type = &*pat_type.ty;
+                            if is_request_context(param_type) {
+                                // Supplied by the server rather than the client
+                                uses_context = true;
+                                param_desers.push(match param_type {
+                                    Type::Reference(_) => quote! { &ctx },
+                                    _ => quote! { ctx.clone() },
+                                });
+                                continue;
+                            }
                             let name_str = param_name.ident.to_string();
                             let is_optional = is_optional_type(param_type);
                             let param_doc = extract_param_doc(&docs, &name_str);
@@ -182,55 +250,73 @@ pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
-            let is_result = matches!(&method.sig.output, ReturnType::Type(_, ty) if matches!(ty.as_ref(), Type::Path(p) if p.path.segments.last().map_or(false, |s| s.ident == "Result")));
+            let is_result = matches!(&method.sig.output, ReturnType::Type(_, ty) if matches!(ty.as_ref(), Type::Path(p) if p.path.segments.last().is_some_and(|s| s.ident == "Result")));
+
+            let error_handling = if returns_tool_error(&method.sig.output) {
+                quote! {
+                    // Execution errors become `isError` results, protocol errors stay errors
+                    Err(e) => e.into_result(),
+                }
+            } else {
+                quote! {
+                    // `{:#}` keeps the context chain of errors such as `anyhow::Error`
+                    Err(e) => offeryn_types::ToolError::execution(format!("{:#}", e)).into_result(),
+                }
+            };
 
             let result_handling = if is_result {
                 quote! {
                     match result {
-                        Ok(result) => Ok(offeryn_types::ToolResult {
-                            content: vec![offeryn_types::ToolContent {
-                                r#type: "text".to_string(),
-                                text: serde_json::to_value(result).map_err(|e| e.to_string())?.to_string(),
-                            }],
-                            is_error: false,
-                        }),
-                        Err(e) => {
-                            // Use a string representation of the error without requiring From implementations
-                            let error_string = format!("{}", e);
+                        Ok(result) => {
+                            let value = serde_json::to_value(result)?;
                             Ok(offeryn_types::ToolResult {
                                 content: vec![offeryn_types::ToolContent {
                                     r#type: "text".to_string(),
-                                    text: error_string,
+                                    text: value.to_string(),
                                 }],
-                                is_error: true,
+                                is_error: false,
+                                // Only JSON objects qualify as structured content
+                                structured_content: value.is_object().then_some(value),
                             })
                         }
+                        #error_handling
                     }
                 }
             } else {
                 quote! {
+                    let value = serde_json::to_value(result)?;
                     Ok(offeryn_types::ToolResult {
                         content: vec![offeryn_types::ToolContent {
                             r#type: "text".to_string(),
-                            text: serde_json::to_value(result).map_err(|e| e.to_string())?.to_string(),
+                            text: value.to_string(),
                         }],
                         is_error: false,
+                        structured_content: value.is_object().then_some(value),
                     })
                 }
             };
 
-            let execute_impl = if param_desers.is_empty() {
+            let ignore_context = (!uses_context).then(|| quote! { let _ = ctx; });
+            let execute_impl = if param_schemas.is_empty() {
                 quote! {
-                    let args = args.as_object().ok_or("Expected object")?;
+                    #ignore_context
+                    let args = args.as_object().ok_or_else(|| {
+                        offeryn_types::ToolError::invalid_arguments("Expected object")
+                    })?;
                     if !args.is_empty() {
-                        return Err("Expected no arguments".to_string());
+                        return Err(offeryn_types::ToolError::invalid_arguments(
+                            "Expected no arguments",
+                        ));
                     }
-                    let result = self.inner.#method_name().await;
+                    let result = self.inner.#method_name(#(#param_desers),*).await;
                     #result_handling
                 }
             } else {
                 quote! {
-                    let args = args.as_object().ok_or("Expected object")?;
+                    #ignore_context
+                    let args = args.as_object().ok_or_else(|| {
+                        offeryn_types::ToolError::invalid_arguments("Expected object")
+                    })?;
                     let result = self.inner.#method_name(#(#param_desers),*).await;
                     #result_handling
                 }
@@ -277,7 +363,11 @@ pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
                     fn name(&self) -> &str { #tool_name }
                     fn description(&self) -> &str { #docs }
                     fn input_schema(&self) -> serde_json::Value { #schema_impl }
-                    async fn execute(&self, args: serde_json::Value) -> Result<offeryn_types::ToolResult, String> {
+                    async fn execute(
+                        &self,
+                        args: serde_json::Value,
+                        ctx: offeryn_types::RequestContext,
+                    ) -> Result<offeryn_types::ToolResult, offeryn_types::ToolError> {
                         #execute_impl
                     }
                 }
@@ -303,3 +393,129 @@ pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
         #(#tool_impls)*
     })
 }
+
+fn generate_prompt_argument_parse(param_type: &Type, param_name: &str) -> proc_macro2::TokenStream {
+    let name_str = param_name.to_string();
+    match option_inner_type(param_type) {
+        Some(inner) => quote! {
+            args.get(#name_str)
+                .map(|v| v.parse::<#inner>())
+                .transpose()
+                .map_err(|e| format!("Invalid value for argument {}: {}", #name_str, e))?
+        },
+        None => quote! {
+            args.get(#name_str)
+                .ok_or_else(|| format!("Missing required argument: {}", #name_str))?
+                .parse::<#param_type>()
+                .map_err(|e| format!("Invalid value for argument {}: {}", #name_str, e))?
+        },
+    }
+}
+
+/// Turns every async method of an `impl` block into an MCP prompt.
+///
+/// Prompt arguments are taken from the method parameters (any `FromStr` type, with
+/// `Option<T>` marking the argument optional) and described from the doc comment the same
+/// way `#[tool]` does. Methods may return a `String`, a `PromptMessage`, a
+/// `Vec<PromptMessage>`, or a `Result` of any of those.
+#[proc_macro_attribute]
+pub fn prompt(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemImpl);
+    let ty = &*input.self_ty;
+    let type_name = impl_type_name(ty);
+
+    let mut prompt_impls = Vec::new();
+    let mut prompt_names = Vec::new();
+
+    for item in &input.items {
+        if let ImplItem::Fn(method) = item {
+            let method_name = &method.sig.ident;
+            let prompt_name = format!("{}_{}", type_name.to_case(Case::Snake), method_name);
+            let prompt_struct_name = format_ident!(
+                "{}{}Prompt",
+                type_name.to_case(Case::Pascal),
+                method_name.to_string().to_case(Case::Pascal)
+            );
+            prompt_names.push(prompt_struct_name.clone());
+
+            let docs = extract_doc_string(&method.attrs);
+
+            let mut arguments = Vec::new();
+            let mut arg_parses = Vec::new();
+
+            for param in &method.sig.inputs {
+                if let FnArg::Typed(pat_type) = param {
+                    if let Pat::Ident(param_name) = &*pat_type.pat {
+                        if param_name.ident != "self" {
+                            let param_type = &*pat_type.ty;
+                            let name_str = param_name.ident.to_string();
+                            let is_required = !is_optional_type(param_type);
+                            let param_doc = extract_param_doc(&docs, &name_str);
+                            let description = if param_doc.is_empty() {
+                                quote! { None }
+                            } else {
+                                quote! { Some(#param_doc.to_string()) }
+                            };
+
+                            arguments.push(quote! {
+                                offeryn_types::PromptArgument {
+                                    name: #name_str.to_string(),
+                                    description: #description,
+                                    required: Some(#is_required),
+                                }
+                            });
+                            arg_parses.push(generate_prompt_argument_parse(param_type, &name_str));
+                        }
+                    }
+                }
+            }
+
+            let prompt_impl = quote! {
+                #[doc(hidden)]
+                pub struct #prompt_struct_name {
+                    inner: std::sync::Arc<#ty>,
+                }
+
+                impl #prompt_struct_name {
+                    pub fn new(inner: std::sync::Arc<#ty>) -> Self {
+                        Self { inner }
+                    }
+                }
+
+                #[async_trait::async_trait]
+                impl offeryn_types::McpPrompt for #prompt_struct_name {
+                    fn name(&self) -> &str { #prompt_name }
+                    fn description(&self) -> &str { #docs }
+                    fn arguments(&self) -> Vec<offeryn_types::PromptArgument> {
+                        vec![#(#arguments),*]
+                    }
+                    async fn get(
+                        &self,
+                        args: std::collections::HashMap<String, String>,
+                    ) -> Result<Vec<offeryn_types::PromptMessage>, String> {
+                        let result = self.inner.#method_name(#(#arg_parses),*).await;
+                        offeryn_types::IntoPromptMessages::into_prompt_messages(result)
+                    }
+                }
+            };
+
+            prompt_impls.push(prompt_impl);
+        }
+    }
+
+    TokenStream::from(quote! {
+        #input
+
+        impl offeryn_types::HasPrompts for #ty {
+            type Prompts = Vec<Box<dyn offeryn_types::McpPrompt>>;
+            fn prompts(self) -> Self::Prompts {
+                let this = std::sync::Arc::new(self);
+                vec![
+                    #(Box::new(#prompt_names::new(this.clone()))),*
+                ]
+            }
+        }
+
+        #(#prompt_impls)*
+    })
+}
@@ -4,4 +4,7 @@ fn test_schema() {
     t.pass("tests/schema/01-basic.rs");
     t.pass("tests/schema/02-doc-comments.rs");
     t.pass("tests/schema/03-stateful.rs");
+    t.pass("tests/schema/04-prompts.rs");
+    t.pass("tests/schema/05-request-context.rs");
+    t.pass("tests/schema/06-tool-errors.rs");
 }
@@ -65,16 +65,16 @@ async fn main() {
 
     // Test actual execution
     let args = serde_json::json!({});
-    let result = get_tool.execute(args).await.unwrap();
+    let result = get_tool.execute(args, RequestContext::default()).await.unwrap();
     assert_eq!(result.content[0].text, "0");
 
     let args = serde_json::json!({
         "by": 5
     });
-    let result = increment_tool.execute(args).await.unwrap();
+    let result = increment_tool.execute(args, RequestContext::default()).await.unwrap();
     assert_eq!(result.content[0].text, "5");
 
     let args = serde_json::json!({});
-    let result = get_tool.execute(args).await.unwrap();
+    let result = get_tool.execute(args, RequestContext::default()).await.unwrap();
     assert_eq!(result.content[0].text, "5");
 }
@@ -0,0 +1,96 @@
+use offeryn_derive::prompt;
+use offeryn_types::*;
+use std::collections::HashMap;
+
+/// Prompts for reviewing code
+#[derive(Default)]
+struct Review {}
+
+#[prompt]
+impl Review {
+    /// Review a code snippet
+    ///
+    /// # Parameters
+    /// * `code` - The code to review
+    /// * `language` - Language the code is written in
+    async fn code(&self, code: String, language: Option<String>) -> Vec<PromptMessage> {
+        vec![
+            PromptMessage::user(format!(
+                "Please review this {} code:\n{}",
+                language.unwrap_or_else(|| "unknown".to_string()),
+                code
+            )),
+            PromptMessage::assistant("Sure, here is my review:"),
+        ]
+    }
+
+    /// Summarize the last few commits
+    ///
+    /// # Parameters
+    /// * `count` - Number of commits to summarize
+    async fn commits(&self, count: u32) -> Result<String, String> {
+        if count == 0 {
+            Err("count must be positive".to_string())
+        } else {
+            Ok(format!("Summarize the last {} commits", count))
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let prompts = Review::default().prompts();
+
+    let code_prompt = &prompts[0];
+    assert_eq!(code_prompt.name(), "review_code");
+    assert_eq!(
+        code_prompt.arguments(),
+        vec![
+            PromptArgument {
+                name: "code".to_string(),
+                description: Some("The code to review".to_string()),
+                required: Some(true),
+            },
+            PromptArgument {
+                name: "language".to_string(),
+                description: Some("Language the code is written in".to_string()),
+                required: Some(false),
+            },
+        ]
+    );
+
+    let mut args = HashMap::new();
+    args.insert("code".to_string(), "fn main() {}".to_string());
+    args.insert("language".to_string(), "rust".to_string());
+    let messages = code_prompt.get(args).await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].role, Role::User);
+    assert_eq!(messages[1].role, Role::Assistant);
+    match &messages[0].content {
+        Content::Text { text } => assert_eq!(text, "Please review this rust code:\nfn main() {}"),
+        _ => panic!("Expected text content"),
+    }
+
+    let missing = code_prompt.get(HashMap::new()).await;
+    assert_eq!(missing.unwrap_err(), "Missing required argument: code");
+
+    let commits_prompt = &prompts[1];
+    assert_eq!(commits_prompt.name(), "review_commits");
+
+    let mut args = HashMap::new();
+    args.insert("count".to_string(), "3".to_string());
+    let messages = commits_prompt.get(args).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].role, Role::User);
+
+    let mut args = HashMap::new();
+    args.insert("count".to_string(), "0".to_string());
+    assert_eq!(
+        commits_prompt.get(args).await.unwrap_err(),
+        "count must be positive"
+    );
+
+    let mut args = HashMap::new();
+    args.insert("count".to_string(), "three".to_string());
+    assert!(commits_prompt.get(args).await.is_err());
+}
@@ -0,0 +1,58 @@
+use offeryn_derive::tool;
+use offeryn_types::*;
+
+/// Tools that look at the request they are serving
+#[derive(Default)]
+struct Inspector {}
+
+#[tool]
+impl Inspector {
+    /// Echo a message along with the request id
+    ///
+    /// # Parameters
+    /// * `message` - Text to echo
+    async fn echo(&self, ctx: RequestContext, message: String) -> String {
+        format!("{} ({:?})", message, ctx.request_id())
+    }
+
+    /// Report the calling session
+    async fn session(&self, ctx: &RequestContext) -> Result<String, String> {
+        ctx.session_id()
+            .map(str::to_string)
+            .ok_or_else(|| "No session".to_string())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let tools = Inspector::default().tools();
+
+    // The context is supplied by the server, not the client
+    let echo_schema = tools[0].input_schema();
+    let properties = echo_schema["properties"].as_object().unwrap();
+    assert_eq!(properties.len(), 1);
+    assert!(properties.contains_key("message"));
+    assert_eq!(echo_schema["required"], serde_json::json!(["message"]));
+
+    let session_schema = tools[1].input_schema();
+    assert!(session_schema["properties"].as_object().unwrap().is_empty());
+
+    let ctx = RequestContext::new(Id::Num(7), None);
+    let result = tools[0]
+        .execute(serde_json::json!({ "message": "hi" }), ctx)
+        .await
+        .unwrap();
+    assert_eq!(result.content[0].text, "\"hi (Num(7))\"");
+
+    // Only the context parameter means no client arguments are accepted
+    let result = tools[1]
+        .execute(serde_json::json!({}), RequestContext::default())
+        .await
+        .unwrap();
+    assert!(result.is_error);
+    assert_eq!(result.content[0].text, "No session");
+    assert!(tools[1]
+        .execute(serde_json::json!({ "ctx": {} }), RequestContext::default())
+        .await
+        .is_err());
+}
@@ -0,0 +1,136 @@
+use offeryn_derive::tool;
+use offeryn_types::*;
+
+#[derive(Debug, thiserror::Error)]
+enum LookupError {
+    #[error("No entry for {0}")]
+    Missing(String),
+}
+
+/// An error that only implements `Display`
+struct Refusal;
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Refused")
+    }
+}
+
+/// Tools failing with the error types applications tend to use
+#[derive(Default)]
+struct Directory {}
+
+#[tool]
+impl Directory {
+    /// Look up an entry
+    ///
+    /// # Parameters
+    /// * `key` - Entry to find
+    async fn lookup(&self, key: String) -> Result<String, LookupError> {
+        Err(LookupError::Missing(key))
+    }
+
+    /// Parse a number
+    ///
+    /// # Parameters
+    /// * `text` - Text to parse
+    async fn parse(&self, text: String) -> anyhow::Result<i64> {
+        let value: i64 = text.parse()?;
+        anyhow::ensure!(value >= 0, "{} is negative", value);
+        Ok(value)
+    }
+
+    /// Read a file
+    ///
+    /// # Parameters
+    /// * `path` - File to read
+    async fn read(&self, path: String) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(std::fs::read_to_string(path)?)
+    }
+
+    /// Check a mode
+    ///
+    /// # Parameters
+    /// * `mode` - Either fast or slow
+    async fn check(&self, mode: String) -> Result<String, ToolError> {
+        match mode.as_str() {
+            "fast" | "slow" => Ok(mode),
+            _ => Err(ToolError::invalid_arguments("mode must be fast or slow")),
+        }
+    }
+
+    /// Refuse to do anything
+    async fn refuse(&self) -> Result<String, Refusal> {
+        Err(Refusal)
+    }
+
+    /// Load a config file
+    ///
+    /// # Parameters
+    /// * `path` - File to load
+    async fn load(&self, path: String) -> anyhow::Result<String> {
+        use anyhow::Context;
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to load {}", path))
+    }
+}
+
+fn assert_error<E: std::error::Error>() {}
+
+async fn failure(tool: &dyn McpTool, args: serde_json::Value) -> String {
+    let result = tool.execute(args, RequestContext::default()).await.unwrap();
+    assert!(result.is_error);
+    result.content[0].text.clone()
+}
+
+#[tokio::main]
+async fn main() {
+    let tools = Directory::default().tools();
+
+    let message = failure(tools[0].as_ref(), serde_json::json!({ "key": "bob" })).await;
+    assert_eq!(message, "No entry for bob");
+
+    let message = failure(tools[1].as_ref(), serde_json::json!({ "text": "-4" })).await;
+    assert_eq!(message, "-4 is negative");
+    let message = failure(tools[1].as_ref(), serde_json::json!({ "text": "four" })).await;
+    assert_eq!(message, "invalid digit found in string");
+
+    let message = failure(
+        tools[2].as_ref(),
+        serde_json::json!({ "path": "/nonexistent/offeryn" }),
+    )
+    .await;
+    assert!(!message.is_empty());
+
+    // Protocol errors are not turned into results
+    let error = tools[3]
+        .execute(serde_json::json!({ "mode": "medium" }), RequestContext::default())
+        .await
+        .unwrap_err();
+    assert!(error.is_protocol_error());
+    let error = tools[3]
+        .execute(serde_json::json!({ "mode": 5 }), RequestContext::default())
+        .await
+        .unwrap_err();
+    assert!(error.is_protocol_error());
+    let error = tools[3]
+        .execute(serde_json::json!({}), RequestContext::default())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error,
+        ToolError::invalid_arguments("Missing required parameter: mode")
+    );
+
+    let message = failure(tools[4].as_ref(), serde_json::json!({})).await;
+    assert_eq!(message, "Refused");
+
+    // The whole context chain is reported
+    let message = failure(
+        tools[5].as_ref(),
+        serde_json::json!({ "path": "/nonexistent/offeryn" }),
+    )
+    .await;
+    assert!(message.starts_with("Failed to load /nonexistent/offeryn: "));
+
+    assert_error::<ToolError>();
+}
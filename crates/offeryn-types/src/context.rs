@@ -0,0 +1,309 @@
+use crate::{
+    protocol_version_at_least, ClientCapabilities, CreateMessageRequest, CreateMessageResult,
+    ElicitRequest, ElicitResult, Elicitation, Implementation, LoggingLevel, LoggingMessageParams,
+    ProgressNotificationParams, ProgressToken, Root, PROTOCOL_VERSION_2025_03_26,
+};
+use async_trait::async_trait;
+use jsonrpc_core::{Error as JsonRpcError, Id};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// The client connection a request arrived on.
+///
+/// Implemented by the server's session type; tools reach it through `RequestContext`.
+#[async_trait]
+pub trait McpPeer: Send + Sync {
+    fn session_id(&self) -> &str;
+    fn protocol_version(&self) -> Option<&str>;
+    fn client_info(&self) -> Option<Implementation>;
+    fn client_capabilities(&self) -> Option<ClientCapabilities>;
+    /// The least severe log level the client wants to receive.
+    fn log_level(&self) -> LoggingLevel;
+    /// Sends a notification to the client. Returns `false` if the client has gone away.
+    fn notify(&self, method: &str, params: Value) -> bool;
+    /// Sends a request to the client and waits for its result.
+    async fn request(&self, method: &str, params: Value) -> Result<Value, PeerError>;
+    /// Asks the client for its roots with `roots/list`.
+    async fn list_roots(&self) -> Result<Vec<Root>, PeerError>;
+    /// Asks the client's model for a completion with `sampling/createMessage`.
+    async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<CreateMessageResult, PeerError>;
+    /// Asks the user for structured input with `elicitation/create`.
+    async fn elicit(&self, request: ElicitRequest) -> Result<ElicitResult, PeerError>;
+}
+
+/// Why a server-to-client request failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerError {
+    /// The request did not arrive on a session, so there is nobody to ask
+    NoSession,
+    /// The client disconnected before answering, or the transport had no way to reach it
+    Disconnected,
+    /// The client answered with a JSON-RPC error
+    Rpc(JsonRpcError),
+    /// The client did not declare the capability the request needs
+    Unsupported(&'static str),
+    /// The client's result did not have the expected shape
+    InvalidResponse(String),
+    /// The request could not be serialized, e.g. because of non-JSON metadata
+    InvalidRequest(String),
+}
+
+impl fmt::Display for PeerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerError::NoSession => write!(f, "No client session"),
+            PeerError::Disconnected => write!(f, "Client disconnected"),
+            PeerError::Rpc(e) => {
+                write!(f, "Client returned error {}: {}", e.code.code(), e.message)
+            }
+            PeerError::Unsupported(capability) => {
+                write!(f, "Client does not support {}", capability)
+            }
+            PeerError::InvalidResponse(e) => write!(f, "Invalid response from client: {}", e),
+            PeerError::InvalidRequest(e) => write!(f, "Invalid request to client: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PeerError {}
+
+/// Everything a tool can know about the request it is serving.
+///
+/// Passed to `McpTool::execute`. `#[tool]` methods receive it by declaring a parameter of
+/// type `RequestContext` or `&RequestContext`, which is left out of the input schema.
+#[derive(Clone)]
+pub struct RequestContext {
+    request_id: Id,
+    peer: Option<Arc<dyn McpPeer>>,
+    progress: Option<Arc<ProgressReporter>>,
+    cancellation: CancellationToken,
+}
+
+impl RequestContext {
+    pub fn new(request_id: Id, peer: Option<Arc<dyn McpPeer>>) -> Self {
+        Self {
+            request_id,
+            peer,
+            progress: None,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Ties the context to a token that fires when the client cancels the request.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Enables progress reporting for a request that carried `_meta.progressToken`.
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// The JSON-RPC id of the request being served.
+    pub fn request_id(&self) -> &Id {
+        &self.request_id
+    }
+
+    /// The session the request arrived on, if any. Requests passed directly to
+    /// `McpServer::handle_request` have none.
+    pub fn session_id(&self) -> Option<&str> {
+        self.peer.as_deref().map(|peer| peer.session_id())
+    }
+
+    pub fn protocol_version(&self) -> Option<&str> {
+        self.peer
+            .as_deref()
+            .and_then(|peer| peer.protocol_version())
+    }
+
+    pub fn client_info(&self) -> Option<Implementation> {
+        self.peer.as_deref().and_then(|peer| peer.client_info())
+    }
+
+    pub fn client_capabilities(&self) -> Option<ClientCapabilities> {
+        self.peer
+            .as_deref()
+            .and_then(|peer| peer.client_capabilities())
+    }
+
+    /// Sends a notification to the client. Returns `false` if there is no session or the
+    /// client has gone away.
+    pub fn notify(&self, method: &str, params: Value) -> bool {
+        self.peer
+            .as_deref()
+            .is_some_and(|peer| peer.notify(method, params))
+    }
+
+    /// Sends a `notifications/message` log entry to the client, unless it is less severe
+    /// than the level the client chose with `logging/setLevel`.
+    pub fn log(&self, level: LoggingLevel, logger: Option<&str>, data: Value) -> bool {
+        if self
+            .peer
+            .as_deref()
+            .is_some_and(|peer| level < peer.log_level())
+        {
+            return false;
+        }
+        let params = LoggingMessageParams {
+            level,
+            logger: logger.map(str::to_string),
+            data,
+        };
+        match serde_json::to_value(params) {
+            Ok(params) => self.notify("notifications/message", params),
+            Err(_) => false,
+        }
+    }
+
+    /// Fires when the client sends `notifications/cancelled` for this request. The server
+    /// stops polling a cancelled tool on its own; work the tool spawned elsewhere should
+    /// watch this token.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// The token the client asked progress to be reported under, if any.
+    pub fn progress_token(&self) -> Option<&ProgressToken> {
+        self.progress.as_deref().map(|progress| &progress.token)
+    }
+
+    /// Sends `notifications/progress` for this request. Does nothing unless the client
+    /// supplied a progress token. Updates arriving faster than the reporter's interval are
+    /// dropped, except the one completing the work. Returns whether a notification was sent.
+    pub fn report_progress(
+        &self,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<&str>,
+    ) -> bool {
+        let (Some(reporter), Some(peer)) = (self.progress.as_deref(), self.peer.as_deref()) else {
+            return false;
+        };
+        let finished = total.is_some_and(|total| progress >= total);
+        if !reporter.try_acquire(finished) {
+            return false;
+        }
+        // Progress messages arrived with 2025-03-26
+        let message = message.filter(|_| {
+            peer.protocol_version().is_some_and(|version| {
+                protocol_version_at_least(version, PROTOCOL_VERSION_2025_03_26)
+            })
+        });
+        let params = ProgressNotificationParams {
+            progress_token: reporter.token.clone(),
+            progress,
+            total,
+            message: message.map(str::to_string),
+        };
+        match serde_json::to_value(params) {
+            Ok(params) => peer.notify("notifications/progress", params),
+            Err(_) => false,
+        }
+    }
+
+    /// Sends a request to the client, such as `roots/list`, and waits for its result.
+    pub async fn send_request(&self, method: &str, params: Value) -> Result<Value, PeerError> {
+        match &self.peer {
+            Some(peer) => peer.request(method, params).await,
+            None => Err(PeerError::NoSession),
+        }
+    }
+
+    /// The roots the client allows the server to operate on. Fails with
+    /// `PeerError::Unsupported` if the client did not declare the `roots` capability.
+    pub async fn roots(&self) -> Result<Vec<Root>, PeerError> {
+        match &self.peer {
+            Some(peer) => peer.list_roots().await,
+            None => Err(PeerError::NoSession),
+        }
+    }
+
+    /// Asks the client's model for a completion, e.g. to summarize intermediate data.
+    /// Fails with `PeerError::Unsupported` if the client did not declare the `sampling`
+    /// capability.
+    pub async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<CreateMessageResult, PeerError> {
+        match &self.peer {
+            Some(peer) => peer.create_message(request).await,
+            None => Err(PeerError::NoSession),
+        }
+    }
+
+    /// Asks the user for a value of type `T` mid-call, e.g. to confirm a destructive
+    /// action or fill in a missing field. The schema shown to the client is generated
+    /// from `T`. Fails with `PeerError::InvalidRequest` if `T` is not a flat object, and
+    /// with `PeerError::Unsupported` if the client did not declare the `elicitation`
+    /// capability.
+    pub async fn elicit<T: JsonSchema + DeserializeOwned>(
+        &self,
+        message: &str,
+    ) -> Result<Elicitation<T>, PeerError> {
+        let Some(peer) = &self.peer else {
+            return Err(PeerError::NoSession);
+        };
+        let request = ElicitRequest::new::<T>(message).map_err(PeerError::InvalidRequest)?;
+        peer.elicit(request)
+            .await?
+            .parse()
+            .map_err(|e| PeerError::InvalidResponse(e.to_string()))
+    }
+}
+
+/// Rate limits the progress notifications of a single request.
+pub struct ProgressReporter {
+    token: ProgressToken,
+    min_interval: Duration,
+    last_sent: Mutex<Option<Instant>>,
+}
+
+impl ProgressReporter {
+    pub fn new(token: ProgressToken, min_interval: Duration) -> Self {
+        Self {
+            token,
+            min_interval,
+            last_sent: Mutex::new(None),
+        }
+    }
+
+    fn try_acquire(&self, force: bool) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        let now = Instant::now();
+        if !force && last_sent.is_some_and(|last| now.duration_since(last) < self.min_interval) {
+            return false;
+        }
+        *last_sent = Some(now);
+        true
+    }
+}
+
+impl Default for RequestContext {
+    /// A context with no session, as used for requests outside any connection.
+    fn default() -> Self {
+        Self::new(Id::Null, None)
+    }
+}
+
+impl fmt::Debug for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestContext")
+            .field("request_id", &self.request_id)
+            .field("session_id", &self.session_id())
+            .finish()
+    }
+}
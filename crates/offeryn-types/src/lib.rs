@@ -1,11 +1,20 @@
+mod context;
+
+pub use context::{McpPeer, PeerError, ProgressReporter, RequestContext};
+pub use tokio_util::sync::CancellationToken;
+
 use async_trait::async_trait;
+use base64::Engine;
 pub use jsonrpc_core::{
     Error as JsonRpcError, Id, Params, Request as JsonRpcRequest, Response as JsonRpcResponse,
     Version,
 };
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolContent {
@@ -17,6 +26,9 @@ pub struct ToolContent {
 pub struct ToolResult {
     pub content: Vec<ToolContent>,
     pub is_error: bool,
+    /// Structured result, sent to clients that negotiated 2025-06-18 or later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
 }
 
 #[async_trait]
@@ -24,7 +36,141 @@ pub trait McpTool: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     fn input_schema(&self) -> Value;
-    async fn execute(&self, args: Value) -> Result<ToolResult, String>;
+    /// Human-readable display name (protocol 2025-06-18 and later)
+    fn title(&self) -> Option<&str> {
+        None
+    }
+    /// Behavioral hints for clients (protocol 2025-03-26 and later)
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        None
+    }
+    /// JSON Schema of the tool's structured output (protocol 2025-06-18 and later)
+    fn output_schema(&self) -> Option<Value> {
+        None
+    }
+    async fn execute(&self, args: Value, ctx: RequestContext) -> Result<ToolResult, ToolError>;
+}
+
+/// Why a tool call failed.
+///
+/// Problems with the call itself, such as arguments that don't match the input schema, are
+/// protocol errors and reach the client as a JSON-RPC error. Anything that goes wrong while
+/// the tool runs is an execution error, reported as a result with `isError: true` so the
+/// model can see what happened and try again.
+///
+/// Strings, I/O errors, JSON errors and `PeerError`s convert into execution errors, so
+/// tools can use `?` on them. `#[tool]` methods may fail with any error type implementing
+/// `Display`; it is reported with `{:#}`, which keeps the context chain of an
+/// `anyhow::Error`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolError {
+    InvalidArguments {
+        message: String,
+    },
+    Execution {
+        message: String,
+        structured_content: Option<Value>,
+    },
+}
+
+impl ToolError {
+    pub fn invalid_arguments(message: impl Into<String>) -> Self {
+        ToolError::InvalidArguments {
+            message: message.into(),
+        }
+    }
+
+    pub fn execution(message: impl Into<String>) -> Self {
+        ToolError::Execution {
+            message: message.into(),
+            structured_content: None,
+        }
+    }
+
+    /// Attaches machine-readable details to an execution error. Protocol errors are left
+    /// unchanged.
+    pub fn with_structured_content(mut self, value: Value) -> Self {
+        if let ToolError::Execution {
+            structured_content, ..
+        } = &mut self
+        {
+            *structured_content = Some(value);
+        }
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ToolError::InvalidArguments { message } | ToolError::Execution { message, .. } => {
+                message
+            }
+        }
+    }
+
+    /// Whether the error should be sent as a JSON-RPC error rather than a tool result.
+    pub fn is_protocol_error(&self) -> bool {
+        matches!(self, ToolError::InvalidArguments { .. })
+    }
+
+    /// Turns an execution error into the `isError` result it is reported as. Protocol
+    /// errors are passed through.
+    pub fn into_result(self) -> Result<ToolResult, ToolError> {
+        match self {
+            ToolError::Execution {
+                message,
+                structured_content,
+            } => Ok(ToolResult {
+                content: vec![ToolContent {
+                    r#type: "text".to_string(),
+                    text: message,
+                }],
+                is_error: true,
+                structured_content,
+            }),
+            error => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolError::InvalidArguments { message } => write!(f, "Invalid arguments: {}", message),
+            ToolError::Execution { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+impl From<String> for ToolError {
+    fn from(message: String) -> Self {
+        ToolError::execution(message)
+    }
+}
+
+impl From<&str> for ToolError {
+    fn from(message: &str) -> Self {
+        ToolError::execution(message)
+    }
+}
+
+impl From<std::io::Error> for ToolError {
+    fn from(error: std::io::Error) -> Self {
+        ToolError::execution(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ToolError {
+    fn from(error: serde_json::Error) -> Self {
+        ToolError::execution(error.to_string())
+    }
+}
+
+impl From<PeerError> for ToolError {
+    fn from(error: PeerError) -> Self {
+        ToolError::execution(error.to_string())
+    }
 }
 
 pub trait HasTools {
@@ -32,38 +178,203 @@ pub trait HasTools {
     fn tools(self) -> Self::Tools;
 }
 
+#[async_trait]
+pub trait McpPrompt: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    fn arguments(&self) -> Vec<PromptArgument>;
+    async fn get(&self, args: HashMap<String, String>) -> Result<Vec<PromptMessage>, String>;
+}
+
+pub trait HasPrompts {
+    type Prompts: IntoIterator<Item = Box<dyn McpPrompt>>;
+    fn prompts(self) -> Self::Prompts;
+}
+
+/// Conversion from the return value of a `#[prompt]` method into prompt messages.
+///
+/// Plain strings become a single user message; errors are stringified.
+pub trait IntoPromptMessages {
+    fn into_prompt_messages(self) -> Result<Vec<PromptMessage>, String>;
+}
+
+impl IntoPromptMessages for String {
+    fn into_prompt_messages(self) -> Result<Vec<PromptMessage>, String> {
+        Ok(vec![PromptMessage::user(self)])
+    }
+}
+
+impl IntoPromptMessages for &str {
+    fn into_prompt_messages(self) -> Result<Vec<PromptMessage>, String> {
+        Ok(vec![PromptMessage::user(self)])
+    }
+}
+
+impl IntoPromptMessages for PromptMessage {
+    fn into_prompt_messages(self) -> Result<Vec<PromptMessage>, String> {
+        Ok(vec![self])
+    }
+}
+
+impl IntoPromptMessages for Vec<PromptMessage> {
+    fn into_prompt_messages(self) -> Result<Vec<PromptMessage>, String> {
+        Ok(self)
+    }
+}
+
+impl<T: IntoPromptMessages, E: std::fmt::Display> IntoPromptMessages for Result<T, E> {
+    fn into_prompt_messages(self) -> Result<Vec<PromptMessage>, String> {
+        self.map_err(|e| format!("{}", e))?.into_prompt_messages()
+    }
+}
+
+/// A concrete resource exposed at a fixed URI.
+#[async_trait]
+pub trait McpResource: Send + Sync {
+    fn uri(&self) -> &str;
+    fn name(&self) -> &str;
+    fn description(&self) -> Option<&str> {
+        None
+    }
+    fn mime_type(&self) -> Option<&str> {
+        None
+    }
+    async fn read(&self) -> Result<Vec<ResourceContents>, String>;
+}
+
+/// A family of resources addressed by an RFC 6570 URI template, e.g. `file:///{path}`.
+///
+/// `read` receives the requested URI along with the variables extracted from it.
+#[async_trait]
+pub trait McpResourceTemplate: Send + Sync {
+    fn uri_template(&self) -> &str;
+    fn name(&self) -> &str;
+    fn description(&self) -> Option<&str> {
+        None
+    }
+    fn mime_type(&self) -> Option<&str> {
+        None
+    }
+    async fn read(
+        &self,
+        uri: &str,
+        variables: HashMap<String, String>,
+    ) -> Result<Vec<ResourceContents>, String>;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Implementation {
     pub name: String,
     pub version: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What the client offers, sent in the `initialize` request.
+///
+/// Clients routinely leave out features they don't support and some add fields of their
+/// own, so every capability is optional and anything unrecognized is kept in `other`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ClientCapabilities {
-    pub experimental: HashMap<String, Value>,
-    pub sampling: HashMap<String, Value>,
-    pub roots: RootsCapability,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<HashMap<String, Value>>,
+    /// Present if the client answers `sampling/createMessage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingCapability>,
+    /// Present if the client answers `roots/list`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<RootsCapability>,
+    /// Present if the client answers `elicitation/create`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elicitation: Option<ElicitationCapability>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RootsCapability {
-    pub list_changed: bool,
+    /// Whether the client emits notifications when its roots change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SamplingCapability {
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ElicitationCapability {
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InitializeParams {
     pub protocol_version: String,
+    #[serde(default)]
     pub capabilities: ClientCapabilities,
     pub client_info: Implementation,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What the server offers, sent in the `initialize` result. A feature the server does not
+/// support is left out entirely.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerCapabilities {
-    pub tools: HashMap<String, bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<ToolsCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<PromptsCapability>,
+    /// Present if the server sends `notifications/message` log entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingCapability>,
+    /// Present if the server answers `completion/complete`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completions: Option<CompletionsCapability>,
+    /// Non-standard capabilities, keyed by name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<HashMap<String, Value>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolsCapability {
+    /// Whether the server emits notifications when the tool list changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoggingCapability {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompletionsCapability {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptsCapability {
+    /// Whether the server emits notifications when the prompt list changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesCapability {
+    /// Whether the server supports subscribing to resource updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscribe: Option<bool>,
+
+    /// Whether the server emits notifications when the resource list changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,8 +403,35 @@ pub struct InitializeResult {
     pub instructions: Option<String>,
 }
 
-pub const LATEST_PROTOCOL_VERSION: &str = "2024-11-05";
-pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2024-11-05"];
+pub const PROTOCOL_VERSION_2024_11_05: &str = "2024-11-05";
+/// Adds tool annotations, audio content and progress messages.
+pub const PROTOCOL_VERSION_2025_03_26: &str = "2025-03-26";
+/// Adds structured tool output and human-readable titles.
+pub const PROTOCOL_VERSION_2025_06_18: &str = "2025-06-18";
+
+pub const LATEST_PROTOCOL_VERSION: &str = PROTOCOL_VERSION_2025_06_18;
+/// Protocol versions this implementation speaks, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[
+    PROTOCOL_VERSION_2025_06_18,
+    PROTOCOL_VERSION_2025_03_26,
+    PROTOCOL_VERSION_2024_11_05,
+];
+
+/// Picks the protocol version to answer an `initialize` request with: the client's
+/// requested version if supported, otherwise the newest supported one.
+pub fn negotiate_protocol_version(requested: &str) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|&&version| version == requested)
+        .copied()
+        .unwrap_or(LATEST_PROTOCOL_VERSION)
+}
+
+/// Whether `negotiated` is at least `required`. Protocol versions are ISO dates, so they
+/// order lexicographically.
+pub fn protocol_version_at_least(negotiated: &str, required: &str) -> bool {
+    negotiated >= required
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -133,6 +471,42 @@ pub struct Tool {
 
     /// JSON Schema describing the tool's input parameters
     pub input_schema: Value, // Using serde_json::Value for the JSON Schema object
+
+    /// Human-readable display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// JSON Schema describing the tool's structured output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+
+    /// Behavioral hints for clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Hints describing a tool's behavior. Clients must treat these as untrusted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// The tool does not modify its environment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+
+    /// The tool may perform destructive updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+
+    /// Repeated calls with the same arguments have no additional effect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+
+    /// The tool interacts with an open world of external entities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -141,6 +515,52 @@ pub struct CallToolRequest {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<std::collections::HashMap<String, Value>>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<RequestMeta>,
+}
+
+/// The `_meta` object a client may attach to any request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestMeta {
+    /// Token to include in `notifications/progress` for this request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress_token: Option<ProgressToken>,
+}
+
+/// Identifies the request a progress notification belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProgressToken {
+    Number(i64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledNotificationParams {
+    /// Id of the request to cancel
+    pub request_id: Id,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressNotificationParams {
+    pub progress_token: ProgressToken,
+
+    /// Work done so far; increases with every notification
+    pub progress: f64,
+
+    /// Total amount of work, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+
+    /// Human-readable description of the current step (protocol 2025-03-26 and later)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -149,6 +569,8 @@ pub struct CallToolResult {
     pub content: Vec<Content>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -163,6 +585,528 @@ pub enum Content {
         mime_type: Option<String>,
     },
 
+    #[serde(rename = "audio", rename_all = "camelCase")]
+    Audio { data: String, mime_type: String },
+
     #[serde(rename = "resource")]
     EmbeddedResource { uri: String, name: Option<String> },
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    /// URI of the resource
+    pub uri: String,
+
+    /// Human-readable name of the resource
+    pub name: String,
+
+    /// Description of what the resource represents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// MIME type of the resource contents, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    /// RFC 6570 URI template used to construct resource URIs
+    pub uri_template: String,
+
+    /// Human-readable name of the resources matched by this template
+    pub name: String,
+
+    /// Description of what the matched resources represent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// MIME type shared by all matched resources, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourcesResult {
+    pub resources: Vec<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourceTemplatesResult {
+    pub resource_templates: Vec<ResourceTemplate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadResourceRequest {
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscribeRequest {
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadResourceResult {
+    pub contents: Vec<ResourceContents>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResourceContents {
+    #[serde(rename_all = "camelCase")]
+    Text {
+        uri: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        text: String,
+    },
+
+    #[serde(rename_all = "camelCase")]
+    Blob {
+        uri: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        /// Base64-encoded binary data
+        blob: String,
+    },
+}
+
+impl ResourceContents {
+    pub fn text(uri: impl Into<String>, mime_type: Option<&str>, text: impl Into<String>) -> Self {
+        ResourceContents::Text {
+            uri: uri.into(),
+            mime_type: mime_type.map(str::to_string),
+            text: text.into(),
+        }
+    }
+
+    /// Builds blob contents, base64-encoding `data`.
+    pub fn blob(uri: impl Into<String>, mime_type: Option<&str>, data: &[u8]) -> Self {
+        ResourceContents::Blob {
+            uri: uri.into(),
+            mime_type: mime_type.map(str::to_string),
+            blob: base64::engine::general_purpose::STANDARD.encode(data),
+        }
+    }
+
+    pub fn uri(&self) -> &str {
+        match self {
+            ResourceContents::Text { uri, .. } | ResourceContents::Blob { uri, .. } => uri,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Prompt {
+    /// Name of the prompt
+    pub name: String,
+
+    /// Description of what the prompt provides
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Arguments used to template the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Vec<PromptArgument>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptArgument {
+    /// Name of the argument
+    pub name: String,
+
+    /// Human-readable description of the argument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Whether the argument must be provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPromptsResult {
+    pub prompts: Vec<Prompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPromptRequest {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPromptResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+impl PromptMessage {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: Content::Text { text: text.into() },
+        }
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: Content::Text { text: text.into() },
+        }
+    }
+}
+
+/// Severity of a `notifications/message` log entry, as in RFC 5424.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingMessageParams {
+    pub level: LoggingLevel,
+
+    /// Name of the logger that produced the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logger: Option<String>,
+
+    /// Arbitrary JSON payload, usually a string or an object
+    pub data: Value,
+}
+
+/// Params of `logging/setLevel`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLevelRequest {
+    /// The least severe level the client wants to receive
+    pub level: LoggingLevel,
+}
+
+/// A directory or file the client allows the server to operate on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Root {
+    /// Currently always a `file://` URI
+    pub uri: String,
+
+    /// Human-readable name, e.g. the name of the project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRootsResult {
+    pub roots: Vec<Root>,
+}
+
+/// Params of `sampling/createMessage`, asking the client's model for a completion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageRequest {
+    pub messages: Vec<SamplingMessage>,
+
+    /// The client decides which model to use; these only guide its choice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<ModelPreferences>,
+
+    /// The client may modify or ignore the system prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Which MCP servers' context the client should include in the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<IncludeContext>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+
+    /// The client may sample fewer tokens than requested
+    pub max_tokens: u32,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+
+    /// Provider-specific options passed through by the client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+impl CreateMessageRequest {
+    pub fn new(messages: Vec<SamplingMessage>, max_tokens: u32) -> Self {
+        Self {
+            messages,
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens,
+            stop_sequences: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    pub fn with_model_preferences(mut self, model_preferences: ModelPreferences) -> Self {
+        self.model_preferences = Some(model_preferences);
+        self
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(system_prompt.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_stop_sequences<S: Into<String>>(
+        mut self,
+        stop_sequences: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.stop_sequences = stop_sequences.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+impl SamplingMessage {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: Content::Text { text: text.into() },
+        }
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: Content::Text { text: text.into() },
+        }
+    }
+}
+
+/// What the server values when the client picks a model. Priorities range from 0 to 1.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    /// Model names to prefer, in order; the client may map them to equivalent models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<ModelHint>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_priority: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_priority: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intelligence_priority: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelHint {
+    /// A full or partial model name, e.g. `claude-3-5-sonnet` or `sonnet`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IncludeContext {
+    None,
+    ThisServer,
+    AllServers,
+}
+
+/// The client's answer to `sampling/createMessage`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: Content,
+
+    /// The model that produced the message
+    pub model: String,
+
+    /// Usually `endTurn`, `stopSequence` or `maxTokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Params of `elicitation/create`, asking the user for structured input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitRequest {
+    /// Shown to the user, explaining what is asked for and why
+    pub message: String,
+
+    /// JSON schema of the answer. Clients only render flat objects whose properties are
+    /// strings, numbers, booleans or enums.
+    pub requested_schema: Value,
+}
+
+impl ElicitRequest {
+    /// Asks for a value of type `T`, using the schema schemars generates for it. Enums are
+    /// inlined as plain string enums, even when their variants are documented, and optional
+    /// fields are left out of `required` rather than made nullable.
+    /// Fails if `T` is not an object of strings, numbers, booleans and enums, since clients
+    /// could not render it.
+    pub fn new<T: JsonSchema>(message: impl Into<String>) -> Result<Self, String> {
+        let mut generator = schemars::gen::SchemaSettings::draft07()
+            .with(|s| {
+                s.inline_subschemas = true;
+                s.option_nullable = false;
+                s.option_add_null_type = false;
+            })
+            .into_generator();
+        let schema = T::json_schema(&mut generator);
+        let mut requested_schema = serde_json::to_value(schema).map_err(|e| e.to_string())?;
+        if requested_schema["type"] != "object" {
+            return Err(format!("{} is not an object", T::schema_name()));
+        }
+        if let Some(properties) = requested_schema["properties"].as_object_mut() {
+            for (name, property) in properties {
+                flatten_string_enum(property);
+                let primitive = property["type"]
+                    .as_str()
+                    .is_some_and(|t| matches!(t, "string" | "number" | "integer" | "boolean"));
+                if !primitive {
+                    return Err(format!(
+                        "Property {} of {} is not a string, number, boolean or enum",
+                        name,
+                        T::schema_name()
+                    ));
+                }
+            }
+        }
+        Ok(Self {
+            message: message.into(),
+            requested_schema,
+        })
+    }
+}
+
+/// Rewrites the `oneOf` schemars generates for an enum with documented variants as a plain
+/// string enum, the only kind of enum elicitation clients render.
+fn flatten_string_enum(property: &mut Value) {
+    let Some(variants) = property.get("oneOf").and_then(Value::as_array) else {
+        return;
+    };
+    let values: Option<Vec<Value>> = variants
+        .iter()
+        .map(|variant| match variant["enum"].as_array() {
+            Some(values) if variant["type"] == "string" && values.len() == 1 => {
+                Some(values[0].clone())
+            }
+            _ => None,
+        })
+        .collect();
+    let (Some(values), Some(property)) = (values, property.as_object_mut()) else {
+        return;
+    };
+    property.remove("oneOf");
+    property.insert("type".to_string(), Value::from("string"));
+    property.insert("enum".to_string(), Value::from(values));
+}
+
+/// How the user responded to an elicitation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitAction {
+    /// Submitted the requested data
+    Accept,
+    /// Explicitly refused to provide it
+    Decline,
+    /// Dismissed the request without choosing
+    Cancel,
+}
+
+/// The client's answer to `elicitation/create`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitResult {
+    pub action: ElicitAction,
+
+    /// The submitted data, present when the user accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<Value>,
+}
+
+impl ElicitResult {
+    /// Parses accepted content as `T`. Content missing from an accepted answer is read as
+    /// an empty object.
+    pub fn parse<T: DeserializeOwned>(self) -> Result<Elicitation<T>, serde_json::Error> {
+        match self.action {
+            ElicitAction::Accept => {
+                let content = self
+                    .content
+                    .unwrap_or_else(|| Value::Object(Default::default()));
+                serde_json::from_value(content).map(Elicitation::Accept)
+            }
+            ElicitAction::Decline => Ok(Elicitation::Decline),
+            ElicitAction::Cancel => Ok(Elicitation::Cancel),
+        }
+    }
+}
+
+/// The user's answer to an elicitation, with accepted content parsed into `T`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Elicitation<T> {
+    Accept(T),
+    Decline,
+    Cancel,
+}
@@ -0,0 +1,68 @@
+use offeryn::types::Content;
+use offeryn::{ChildProcessTransport, McpClient};
+use serde_json::json;
+use tokio::process::Command;
+
+#[tokio::test]
+async fn test_spawn_stdio_calculator() {
+    let transport =
+        ChildProcessTransport::new(Command::new(env!("CARGO_BIN_EXE_stdio-calculator")));
+    let client = McpClient::connect(transport, "test-client", "1.0.0")
+        .await
+        .unwrap();
+    assert_eq!(client.server().server_info.name, "calculator");
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools.tools.len(), 4);
+
+    let result = client
+        .call_tool("calculator_multiply", json!({ "a": 6.0, "b": 7.0 }))
+        .await
+        .unwrap();
+    match &result.content[0] {
+        Content::Text { text } => assert_eq!(text, "42.0"),
+        _ => panic!("Expected text content"),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_dropping_client_stops_server() {
+    use std::time::Duration;
+
+    // A server that outlives its stdin: once the calculator exits the shell is replaced by
+    // a long sleep holding on to stdout
+    let pid_file =
+        std::env::temp_dir().join(format!("stdio-calculator-{}.pid", std::process::id()));
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(r#"echo $$ > "$1"; "$2"; exec sleep 60"#)
+        .arg("sh")
+        .arg(&pid_file)
+        .arg(env!("CARGO_BIN_EXE_stdio-calculator"));
+
+    let client = McpClient::connect(ChildProcessTransport::new(command), "test-client", "1.0.0")
+        .await
+        .unwrap();
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+    let _ = std::fs::remove_file(&pid_file);
+    let is_running = || {
+        std::process::Command::new("kill")
+            .args(["-0", pid.trim()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap()
+            .success()
+    };
+    assert!(is_running());
+
+    drop(client);
+    for _ in 0..100 {
+        if !is_running() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("server process still running after the client was dropped");
+}
@@ -1,8 +1,17 @@
-pub use offeryn_core::{transport::SseServerTransport, transport::StdioServerTransport, McpServer};
-pub use offeryn_derive::tool;
+pub use offeryn_core::{
+    client::{ChildProcessTransport, SseClientTransport, StdioClientTransport},
+    transport::SseServerTransport,
+    transport::StdioServerTransport,
+    transport::StreamableHttpServerTransport,
+    transport::WebSocketServerTransport,
+    LoggingLayer, McpClient, McpServer, NotificationHandler, ResourceNotifier, Session,
+    SessionState,
+};
+pub use offeryn_derive::{prompt, tool};
 pub use offeryn_types as types;
 
 pub mod prelude {
+    pub use crate::prompt as mcp_prompt;
     pub use crate::tool as mcp_tool;
     pub use offeryn_types;
     pub use schemars;